@@ -1,33 +1,444 @@
 #![cfg_attr(not(feature = "std"), no_std, no_main)]
 
 #[ink::contract]
-mod erc20 {
+pub mod erc20 {
+    use ink::env::call::{build_call, ExecutionInput, Selector};
+    use ink::env::chain_extension::ChainExtensionMethod;
+    use ink::env::hash::{Blake2x256, HashOutput};
+    use ink::env::DefaultEnvironment;
+    use ink::prelude::{string::String, vec::Vec};
     use ink::storage::Mapping;
 
+    /// Identifier of a role granted through the access-control messages.
+    pub type RoleId = u32;
+
+    /// Role allowed to mint new tokens.
+    pub const MINTER: RoleId = ink::selector_id!("MINTER");
+
+    /// Role allowed to pause and unpause the token.
+    pub const PAUSER: RoleId = ink::selector_id!("PAUSER");
+
+    /// Role allowed to rebase the supply, such as a price oracle.
+    pub const REBASER: RoleId = ink::selector_id!("REBASER");
+
+    /// Role allowed to take balance snapshots.
+    pub const SNAPSHOTTER: RoleId = ink::selector_id!("SNAPSHOTTER");
+
+    /// Identifier of a balance snapshot; ids start at 1.
+    pub type SnapshotId = u32;
+
+    /// Shares an account held at a snapshot.
+    type ShareCheckpoint = (SnapshotId, Balance);
+
+    /// Total supply and total shares at a snapshot.
+    type SupplyCheckpoint = (SnapshotId, Balance, Balance);
+
+    /// Voting power of a delegate from a block on.
+    type VoteCheckpoint = (BlockNumber, Balance);
+
+    /// Longest payment tag that can be registered, in bytes.
+    pub const MAX_TAG_LEN: usize = 32;
+
+    /// Most recipients a single `transfer_batch` may pay, keeping the weight
+    /// of one call bounded.
+    pub const MAX_BATCH_LEN: usize = 64;
+
+    /// Largest travel-rule envelope `transfer_with_envelope` accepts, in bytes.
+    pub const MAX_ENVELOPE_LEN: usize = 1024;
+
+    /// Basis points in a whole; the highest transfer fee is 100%.
+    pub const BPS_DENOMINATOR: u16 = 10_000;
+
+    /// Highest share of a transfer that may be burned, in basis points.
+    pub const MAX_BURN_BPS: u16 = 1_000;
+
+    /// An ERC-20 style fungible token.
+    ///
+    /// No constructor or message is payable, so the dispatcher rejects any
+    /// call that carries native currency. Funds sent to the contract account
+    /// by a plain balance transfer are not tracked by the token and cannot be
+    /// withdrawn through it.
     #[ink(storage)]
     #[derive(Default)]
     pub struct Erc20 {
         total_supply: Balance,
+        /// Shares held by each account; a balance is its share of
+        /// `total_supply`. Shares and balances are equal until a rebase.
         balances: Mapping<AccountId, Balance>,
+        total_shares: Balance,
         allowances: Mapping<(AccountId, AccountId), Balance>,
+        /// Hash of the contract address and instantiation block.
+        deployment_id: Hash,
+        /// Chain the token was deployed on, as given at instantiation.
+        chain_id: Hash,
+        /// Owners that opted out of the contract-spenders-only check.
+        allows_plain_spenders: Mapping<AccountId, ()>,
+        /// Accounts that reject incoming transfers.
+        refuses_incoming: Mapping<AccountId, ()>,
+        /// Payment tags and the accounts they resolve to.
+        tags: Mapping<String, AccountId>,
+        /// Release schedule of the allocations made at construction, if any.
+        genesis_schedule: Option<GenesisSchedule>,
+        /// Allocation each account received at construction under the
+        /// genesis schedule, in shares.
+        genesis_allocations: Mapping<AccountId, Balance>,
+        name: Option<String>,
+        symbol: Option<String>,
+        decimals: u8,
+        /// Upper bound on the total supply, if any.
+        cap: Option<Balance>,
+        /// Roles held by each account.
+        roles: Mapping<(RoleId, AccountId), ()>,
+        /// PSP22 tokens held by the locker, by lock id.
+        token_locks: Mapping<u32, TokenLock>,
+        next_lock_id: u32,
+        /// Amount of each PSP22 token currently held by the locker; shares
+        /// for this token.
+        total_locked: Mapping<AccountId, Balance>,
+        /// Account allowed to perform privileged operations, `None` once
+        /// ownership has been renounced.
+        owner: Option<AccountId>,
+        /// Account that `transfer_ownership` nominated as the next owner.
+        pending_owner: Option<AccountId>,
+        /// Whether transfers, approvals, minting and burning are halted.
+        paused: bool,
+        /// Number of signed permits and meta-transactions each account has
+        /// used; signs the next one.
+        nonces: Mapping<AccountId, u64>,
+        /// Account allowed to relay meta-transactions, if any.
+        trusted_forwarder: Option<AccountId>,
+        /// Chain extension function notified of every transfer, if any.
+        transfer_extension: Option<u32>,
+        /// Root of the current airdrop's merkle tree, if any.
+        merkle_root: Option<Hash>,
+        /// Claimed airdrop leaves, 128 per word, keyed by root and word.
+        claimed: Mapping<(Hash, u32), u128>,
+        /// Vesting grant of each beneficiary; the tokens sit with the contract.
+        vesting_grants: Mapping<AccountId, VestingGrant>,
+        /// Blocks between scheduling and executing an admin operation.
+        min_delay: BlockNumber,
+        /// Block from which each scheduled admin operation may run, and the
+        /// owner who scheduled it, by id.
+        scheduled_operations: Mapping<Hash, (BlockNumber, AccountId)>,
+        /// Share of each transfer paid to `fee_collector`, in basis points.
+        fee_bps: u16,
+        /// Account receiving transfer fees; no fee is charged without one.
+        fee_collector: Option<AccountId>,
+        /// Accounts whose transfers, in either direction, pay no fee.
+        fee_exempt: Mapping<AccountId, ()>,
+        /// Share of each transfer burned, in basis points.
+        burn_bps: u16,
+        /// Transfer fees each account has paid, in tokens at the time.
+        fees_paid: Mapping<AccountId, Balance>,
+        /// Amounts burned from each account, through transfers or
+        /// explicitly, in tokens at the time.
+        burned: Mapping<AccountId, Balance>,
+        current_snapshot: SnapshotId,
+        /// Shares each account held at a snapshot, recorded the first time
+        /// they change after it. Indexed by position, oldest first.
+        share_checkpoints: Mapping<(AccountId, u32), ShareCheckpoint>,
+        share_checkpoint_count: Mapping<AccountId, u32>,
+        /// Total supply and total shares at a snapshot, recorded the same way.
+        supply_checkpoints: Mapping<u32, SupplyCheckpoint>,
+        supply_checkpoint_count: u32,
+        /// Account each holder's voting power is delegated to.
+        delegates: Mapping<AccountId, AccountId>,
+        /// Voting power of each delegate over time, oldest first.
+        vote_checkpoints: Mapping<(AccountId, u32), VoteCheckpoint>,
+        vote_checkpoint_count: Mapping<AccountId, u32>,
+    }
+
+    /// PSP22 tokens deposited into the locker until `unlock_at`. Locks of
+    /// this token hold shares in storage, so they follow rebases; queries
+    /// report them in tokens.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct TokenLock {
+        pub token: AccountId,
+        pub owner: AccountId,
+        pub amount: Balance,
+        pub unlock_at: Timestamp,
+    }
+
+    /// Tokens held for `beneficiary` that vest linearly over `duration` from
+    /// `start`, with nothing vested before `start + cliff`. Times are
+    /// timestamps, like `TokenLock::unlock_at`. `total` and `released` are
+    /// shares, so a grant follows rebases like any balance.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct VestingGrant {
+        pub total: Balance,
+        pub released: Balance,
+        pub start: Timestamp,
+        pub cliff: Timestamp,
+        pub duration: Timestamp,
+    }
+
+    /// Admin operation that goes through the timelock.
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum AdminOperation {
+        GrantRole(RoleId, AccountId),
+        RevokeRole(RoleId, AccountId),
+        SetTrustedForwarder(Option<AccountId>),
+        SetTransferExtension(Option<u32>),
+        SetMerkleRoot(Hash),
+        SetMinDelay(BlockNumber),
+        SetFeeBps(u16),
+        SetFeeCollector(Option<AccountId>),
+        AddFeeExempt(AccountId),
+        RemoveFeeExempt(AccountId),
+        SetBurnBps(u16),
+        Rebase(i128),
+    }
+
+    /// Releases genesis allocations in `tranches` equal parts, one at the end
+    /// of every `epoch_length` blocks counted from `start`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct GenesisSchedule {
+        pub start: BlockNumber,
+        pub epoch_length: BlockNumber,
+        pub tranches: u32,
     }
 
     #[ink(event)]
     pub struct Transfer {
         #[ink(topic)]
-        from: Option<AccountId>,
+        pub from: Option<AccountId>,
         #[ink(topic)]
-        to: Option<AccountId>,
-        value: Balance,
+        pub to: Option<AccountId>,
+        pub value: Balance,
     }
 
     #[ink(event)]
     pub struct Approval {
         #[ink(topic)]
-        from: AccountId,
+        pub from: AccountId,
+        #[ink(topic)]
+        pub to: AccountId,
+        pub value: Balance,
+    }
+
+    // Every event after `Transfer` and `Approval` names the account that
+    // acted as its first field and first topic; ink! publishes the event
+    // signature ahead of it, which tells the events apart.
+
+    #[ink(event)]
+    pub struct OwnershipTransferred {
+        #[ink(topic)]
+        pub caller: AccountId,
+        #[ink(topic)]
+        pub previous_owner: Option<AccountId>,
+        #[ink(topic)]
+        pub new_owner: Option<AccountId>,
+    }
+
+    #[ink(event)]
+    pub struct RoleGranted {
+        #[ink(topic)]
+        pub sender: AccountId,
+        #[ink(topic)]
+        pub role: RoleId,
+        #[ink(topic)]
+        pub account: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct RoleRevoked {
+        #[ink(topic)]
+        pub sender: AccountId,
+        #[ink(topic)]
+        pub role: RoleId,
+        #[ink(topic)]
+        pub account: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct Paused {
+        #[ink(topic)]
+        pub account: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct Unpaused {
+        #[ink(topic)]
+        pub account: AccountId,
+    }
+
+    /// Travel-rule information attached to the `Transfer` emitted just
+    /// before it. `envelope` is encrypted originator and beneficiary data
+    /// that only the VASPs involved can read.
+    #[ink(event)]
+    pub struct TravelRuleEnvelope {
+        #[ink(topic)]
+        pub from: AccountId,
+        #[ink(topic)]
+        pub to: AccountId,
+        pub value: Balance,
+        pub envelope: Vec<u8>,
+    }
+
+    #[ink(event)]
+    pub struct Claimed {
+        #[ink(topic)]
+        pub account: AccountId,
+        pub index: u32,
+        pub amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct VestingCreated {
+        #[ink(topic)]
+        pub caller: AccountId,
+        #[ink(topic)]
+        pub beneficiary: AccountId,
+        pub total: Balance,
+        pub start: Timestamp,
+        pub cliff: Timestamp,
+        pub duration: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct TokensReleased {
+        #[ink(topic)]
+        pub beneficiary: AccountId,
+        pub amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct OperationScheduled {
+        #[ink(topic)]
+        pub caller: AccountId,
+        #[ink(topic)]
+        pub id: Hash,
+        pub operation: AdminOperation,
+        pub ready_at: BlockNumber,
+    }
+
+    #[ink(event)]
+    pub struct OperationExecuted {
+        #[ink(topic)]
+        pub caller: AccountId,
+        #[ink(topic)]
+        pub id: Hash,
+    }
+
+    #[ink(event)]
+    pub struct OperationCancelled {
+        #[ink(topic)]
+        pub caller: AccountId,
+        #[ink(topic)]
+        pub id: Hash,
+    }
+
+    #[ink(event)]
+    pub struct Rebased {
+        #[ink(topic)]
+        pub caller: AccountId,
+        pub previous_supply: Balance,
+        pub total_supply: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Snapshot {
+        #[ink(topic)]
+        pub caller: AccountId,
+        pub id: SnapshotId,
+    }
+
+    #[ink(event)]
+    pub struct DelegateChanged {
+        #[ink(topic)]
+        pub delegator: AccountId,
+        #[ink(topic)]
+        pub from_delegate: Option<AccountId>,
+        #[ink(topic)]
+        pub to_delegate: Option<AccountId>,
+    }
+
+    #[ink(event)]
+    pub struct DelegateVotesChanged {
+        #[ink(topic)]
+        pub caller: AccountId,
+        #[ink(topic)]
+        pub delegate: AccountId,
+        pub previous_votes: Balance,
+        pub new_votes: Balance,
+    }
+
+    #[ink(event)]
+    pub struct TokensLocked {
+        #[ink(topic)]
+        pub owner: AccountId,
+        #[ink(topic)]
+        pub token: AccountId,
+        pub id: u32,
+        pub amount: Balance,
+        pub unlock_at: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct LockWithdrawn {
+        #[ink(topic)]
+        pub owner: AccountId,
+        #[ink(topic)]
+        pub token: AccountId,
+        pub id: u32,
+        pub amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct FeeBpsChanged {
+        #[ink(topic)]
+        pub sender: AccountId,
+        pub fee_bps: u16,
+    }
+
+    #[ink(event)]
+    pub struct FeeCollectorChanged {
+        #[ink(topic)]
+        pub sender: AccountId,
+        #[ink(topic)]
+        pub collector: Option<AccountId>,
+    }
+
+    #[ink(event)]
+    pub struct FeeExemptionChanged {
+        #[ink(topic)]
+        pub sender: AccountId,
+        #[ink(topic)]
+        pub account: AccountId,
+        pub exempt: bool,
+    }
+
+    #[ink(event)]
+    pub struct BurnBpsChanged {
+        #[ink(topic)]
+        pub sender: AccountId,
+        pub burn_bps: u16,
+    }
+
+    /// Diagnostic trace of a state-mutating call. Only emitted when the
+    /// `telemetry` feature is enabled.
+    #[ink(event)]
+    pub struct CallTrace {
         #[ink(topic)]
-        to: AccountId,
-        value: Balance,
+        pub caller: AccountId,
+        /// Code hash of the caller, if it is a contract.
+        pub caller_code_hash: Option<Hash>,
+        /// Zero for a call straight from the transaction origin; `None` for
+        /// a nested call, whose depth pallet-contracts does not expose.
+        pub call_depth: Option<u32>,
     }
 
     #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
@@ -35,13 +446,179 @@ mod erc20 {
     pub enum Error {
         BalanceTooLow,
         AllowanceToolow,
+        Overflow,
+        SpenderNotContract,
+        RecipientRefused,
+        InvalidTag,
+        TagTaken,
+        UnknownTag,
+        InvalidSchedule,
+        TokensLocked,
+        NotMinter,
+        InvalidUnlockTime,
+        UnknownLock,
+        NotLockOwner,
+        StillLocked,
+        TokenTransferFailed,
+        NotOwner,
+        NotPendingOwner,
+        NotPauser,
+        ContractPaused,
+        CapExceeded,
+        Underflow,
+        ReceiverRejected,
+        SignatureExpired,
+        NotTrustedForwarder,
+        BatchTooLarge,
+        EnvelopeTooLarge,
+        InvalidProof,
+        AlreadyClaimed,
+        GrantExists,
+        NoGrant,
+        Timelocked,
+        OperationExists,
+        UnknownOperation,
+        OperationNotReady,
+        InvalidFee,
+        NotRebaser,
+        InvalidRebase,
+        NotSnapshotter,
+        UnknownSnapshot,
+        FutureLookup,
+        InvalidSignature,
+    }
+
+    /// Error type of the PSP22 standard, returned by this token's PSP22
+    /// messages and by the PSP22 tokens the locker calls into.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum PSP22Error {
+        Custom(String),
+        InsufficientBalance,
+        InsufficientAllowance,
+        ZeroRecipientAddress,
+        ZeroSenderAddress,
+        SafeTransferCheckFailed(String),
+    }
+
+    impl From<Error> for PSP22Error {
+        fn from(error: Error) -> Self {
+            match error {
+                Error::BalanceTooLow => PSP22Error::InsufficientBalance,
+                Error::AllowanceToolow => PSP22Error::InsufficientAllowance,
+                other => PSP22Error::Custom(ink::prelude::format!("{other:?}")),
+            }
+        }
+    }
+
+    /// The PSP22 fungible token standard, with the selectors its
+    /// specification fixes.
+    #[ink::trait_definition]
+    pub trait PSP22 {
+        #[ink(message, selector = 0x162DF8C2)]
+        fn total_supply(&self) -> Balance;
+
+        #[ink(message, selector = 0x6568382F)]
+        fn balance_of(&self, owner: AccountId) -> Balance;
+
+        #[ink(message, selector = 0x4D47D921)]
+        fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance;
+
+        #[ink(message, selector = 0xDB20F9F5)]
+        fn transfer(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            data: Vec<u8>,
+        ) -> core::result::Result<(), PSP22Error>;
+
+        #[ink(message, selector = 0x54B3C76E)]
+        fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            data: Vec<u8>,
+        ) -> core::result::Result<(), PSP22Error>;
+
+        #[ink(message, selector = 0xB20F1BBD)]
+        fn approve(
+            &mut self,
+            spender: AccountId,
+            value: Balance,
+        ) -> core::result::Result<(), PSP22Error>;
+
+        #[ink(message, selector = 0x96D6B57A)]
+        fn increase_allowance(
+            &mut self,
+            spender: AccountId,
+            delta_value: Balance,
+        ) -> core::result::Result<(), PSP22Error>;
+
+        #[ink(message, selector = 0xFECB57D5)]
+        fn decrease_allowance(
+            &mut self,
+            spender: AccountId,
+            delta_value: Balance,
+        ) -> core::result::Result<(), PSP22Error>;
+    }
+
+    /// The PSP22Metadata extension, with its standard selectors.
+    #[ink::trait_definition]
+    pub trait PSP22Metadata {
+        #[ink(message, selector = 0x3D261BD4)]
+        fn token_name(&self) -> Option<String>;
+
+        #[ink(message, selector = 0x34205BE5)]
+        fn token_symbol(&self) -> Option<String>;
+
+        #[ink(message, selector = 0x7271B782)]
+        fn token_decimals(&self) -> u8;
     }
 
     type Result<T> = core::result::Result<T, Error>;
 
+    /// Outcome of a transfer as reported by `simulate_transfer`.
+    ///
+    /// The amounts describe how `value` would be split if the transfer went
+    /// through; `blocked_by` is the error the transfer would fail with.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct TransferSimulation {
+        /// Amount credited to the recipient.
+        pub net: Balance,
+        /// Amount paid as fees.
+        pub fee: Balance,
+        /// Amount burned.
+        pub burned: Balance,
+        pub blocked_by: Option<Error>,
+    }
+
+    /// Every message pins its selector explicitly, using the value ink! derived
+    /// from its name when it was introduced, so renames and trait refactors
+    /// cannot change the ABI. `message_selectors_are_stable` checks them.
     impl Erc20 {
+        /// Creates the token with `total_supply` held by the deployer. When
+        /// `cap` is set, minting can never push the supply above it.
+        /// `chain_id`, usually the chain's genesis hash, goes into the
+        /// domain separator, since contracts cannot query it.
+        ///
+        /// # Panics
+        ///
+        /// If `total_supply` already exceeds `cap`.
         #[ink(constructor)]
-        pub fn new(total_supply: Balance) -> Self {
+        pub fn new(
+            total_supply: Balance,
+            name: Option<String>,
+            symbol: Option<String>,
+            decimals: u8,
+            cap: Option<Balance>,
+            chain_id: Hash,
+        ) -> Self {
+            assert!(
+                Self::check_cap(total_supply, cap).is_ok(),
+                "initial supply exceeds cap"
+            );
             let mut balances = Mapping::new();
             balances.insert(Self::env().caller(), &total_supply);
 
@@ -50,141 +627,4267 @@ mod erc20 {
                 to: Some(Self::env().caller()),
                 value: total_supply,
             });
+            Self::env().emit_event(OwnershipTransferred {
+                caller: Self::env().caller(),
+                previous_owner: None,
+                new_owner: Some(Self::env().caller()),
+            });
+            let mut roles = Mapping::new();
+            roles.insert((MINTER, Self::env().caller()), &());
+            Self::env().emit_event(RoleGranted {
+                sender: Self::env().caller(),
+                role: MINTER,
+                account: Self::env().caller(),
+            });
+
+            let mut deployment_id = <Blake2x256 as HashOutput>::Type::default();
+            ink::env::hash_encoded::<Blake2x256, _>(
+                &(Self::env().account_id(), Self::env().block_number()),
+                &mut deployment_id,
+            );
 
             Self {
                 total_supply,
                 balances,
-                allowances: Default::default(),
+                total_shares: total_supply,
+                deployment_id: deployment_id.into(),
+                chain_id,
+                name,
+                symbol,
+                decimals,
+                cap,
+                roles,
+                owner: Some(Self::env().caller()),
+                ..Default::default()
             }
         }
 
-        /// A message that can be called on instantiated contracts.
-        /// This one flips the value of the stored `bool` from `true`
-        /// to `false` and vice versa.
-        #[ink(message)]
+        /// Creates the token and hands out `allocations` from the deployer's
+        /// supply. Allocated tokens stay locked and unlock in `tranches`
+        /// equal parts, one per `epoch_length` blocks.
+        #[ink(constructor)]
+        #[allow(clippy::too_many_arguments)]
+        pub fn new_with_genesis_locks(
+            total_supply: Balance,
+            name: Option<String>,
+            symbol: Option<String>,
+            decimals: u8,
+            cap: Option<Balance>,
+            chain_id: Hash,
+            allocations: Vec<(AccountId, Balance)>,
+            epoch_length: BlockNumber,
+            tranches: u32,
+        ) -> Result<Self> {
+            if epoch_length == 0 || tranches == 0 {
+                return Err(Error::InvalidSchedule);
+            }
+            Self::check_cap(total_supply, cap)?;
+
+            let mut erc20 = Self::new(total_supply, name, symbol, decimals, cap, chain_id);
+            let deployer = Self::env().caller();
+            for (account, value) in allocations {
+                let shares = erc20.to_shares(value)?;
+                if account != deployer {
+                    erc20.transfer_helper(&deployer, &account, value)?;
+                }
+                let allocated = erc20
+                    .genesis_allocations
+                    .get(account)
+                    .unwrap_or_default()
+                    .checked_add(shares)
+                    .ok_or(Error::Overflow)?;
+                erc20.genesis_allocations.insert(account, &allocated);
+            }
+            erc20.genesis_schedule = Some(GenesisSchedule {
+                start: Self::env().block_number(),
+                epoch_length,
+                tranches,
+            });
+
+            Ok(erc20)
+        }
+
+        /// Creates the token from a supply given in whole tokens, scaled by
+        /// `10^decimals` into base units.
+        #[ink(constructor)]
+        pub fn new_human(
+            supply_whole_tokens: Balance,
+            name: Option<String>,
+            symbol: Option<String>,
+            decimals: u8,
+            cap: Option<Balance>,
+            chain_id: Hash,
+        ) -> Result<Self> {
+            let total_supply = Balance::from(10u8)
+                .checked_pow(u32::from(decimals))
+                .and_then(|unit| supply_whole_tokens.checked_mul(unit))
+                .ok_or(Error::Overflow)?;
+            Self::check_cap(total_supply, cap)?;
+            Ok(Self::new(
+                total_supply,
+                name,
+                symbol,
+                decimals,
+                cap,
+                chain_id,
+            ))
+        }
+
+        /// Returns the highest total supply minting may reach, if capped.
+        #[ink(message, selector = 0xB00B03C6)]
+        pub fn cap(&self) -> Option<Balance> {
+            self.cap
+        }
+
+        /// Returns the total token supply.
+        #[ink(message, selector = 0xDB6375A8)]
         pub fn total_supply(&self) -> Balance {
             self.total_supply
         }
 
-        /// Simply returns the current value of our `bool`.
-        #[ink(message)]
+        #[ink(message, selector = 0x53DB29AE)]
+        pub fn token_name(&self) -> Option<String> {
+            self.name.clone()
+        }
+
+        #[ink(message, selector = 0xD733EA17)]
+        pub fn token_symbol(&self) -> Option<String> {
+            self.symbol.clone()
+        }
+
+        /// Number of decimals a whole token is divided into, for display.
+        #[ink(message, selector = 0x9715E3CA)]
+        pub fn token_decimals(&self) -> u8 {
+            self.decimals
+        }
+
+        /// Returns the balance of `who`, zero for unknown accounts.
+        #[ink(message, selector = 0x0F755A56)]
         pub fn balance_of(&self, who: AccountId) -> Balance {
-            self.balances.get(&who).unwrap_or_default()
+            self.to_balance(self.shares_of(who))
         }
 
-        #[ink(message)]
+        /// Returns how much `spender` may still transfer on behalf of `owner`.
+        #[ink(message, selector = 0x6A00165E)]
+        pub fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            self.allowances.get((owner, spender)).unwrap_or_default()
+        }
+
+        /// Balances of `accounts`, in the same order.
+        #[ink(message, selector = 0x97D5F295)]
+        pub fn balances_of(&self, accounts: Vec<AccountId>) -> Vec<Balance> {
+            accounts
+                .into_iter()
+                .map(|account| self.balance_of(account))
+                .collect()
+        }
+
+        /// Allowances of the `(owner, spender)` pairs, in the same order.
+        #[ink(message, selector = 0x3ED08818)]
+        pub fn allowances_of(&self, pairs: Vec<(AccountId, AccountId)>) -> Vec<Balance> {
+            pairs
+                .into_iter()
+                .map(|(owner, spender)| self.allowance(owner, spender))
+                .collect()
+        }
+
+        /// Part of the genesis allocation of `account` that is still locked.
+        #[ink(message, selector = 0xA3B7D8EB)]
+        pub fn locked_balance_of(&self, account: AccountId) -> Balance {
+            let Some(schedule) = self.genesis_schedule else {
+                return 0;
+            };
+            let allocated = self.genesis_allocations.get(account).unwrap_or_default();
+            let elapsed = self.env().block_number().saturating_sub(schedule.start);
+            let released_tranches = Balance::from(core::cmp::min(
+                elapsed / schedule.epoch_length,
+                schedule.tranches,
+            ));
+            let tranches = Balance::from(schedule.tranches);
+
+            // At most `allocated`, as no more tranches than exist are released.
+            let released =
+                Self::mul_div(allocated, released_tranches, tranches).unwrap_or(allocated);
+            self.to_balance(allocated - released)
+        }
+
+        #[ink(message, selector = 0x14F5EA79)]
+        pub fn genesis_schedule(&self) -> Option<GenesisSchedule> {
+            self.genesis_schedule
+        }
+
+        /// Identifies this deployment across chains running the same code.
+        #[ink(message, selector = 0xC4D9FB2D)]
+        pub fn deployment_id(&self) -> Hash {
+            self.deployment_id
+        }
+
+        #[ink(message, selector = 0xFFE9F9CF)]
+        pub fn chain_id(&self) -> Hash {
+            self.chain_id
+        }
+
+        #[ink(message, selector = 0x84A15DA1)]
         pub fn transfer(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            self.trace_call();
             let sender = self.env().caller();
             self.transfer_helper(&sender, &to, value)
         }
 
-        #[ink(message)]
+        #[ink(message, selector = 0x0B396F18)]
         pub fn transfer_from(
             &mut self,
             from: AccountId,
             to: AccountId,
             value: Balance,
         ) -> Result<()> {
+            self.trace_call();
             let sender = self.env().caller();
-            let allowance = self.allowances.get(&(from, sender)).unwrap_or_default();
-
-            if allowance < value {
-                return Err(Error::AllowanceToolow);
-            }
-
-            self.allowances.insert((from, sender), &(allowance - value));
-
+            self.spend_allowance(&from, &sender, value)?;
             self.transfer_helper(&from, &to, value)
         }
 
-        #[ink(message)]
-        pub fn approve(&mut self, spender: AccountId, value: Balance) -> Result<()> {
+        /// Pays every `(recipient, value)` pair from the caller's balance,
+        /// emitting one `Transfer` per recipient. All transfers are checked
+        /// against the combined total before any is applied, and either all
+        /// of them go through or none does.
+        #[ink(message, selector = 0x810861B9)]
+        pub fn transfer_batch(&mut self, transfers: Vec<(AccountId, Balance)>) -> Result<()> {
+            self.trace_call();
+            if transfers.len() > MAX_BATCH_LEN {
+                return Err(Error::BatchTooLarge);
+            }
             let sender = self.env().caller();
-            self.allowances.insert((sender, spender), &value);
+            let total = transfers
+                .iter()
+                .try_fold(0, |total: Balance, (_, value)| total.checked_add(*value))
+                .ok_or(Error::Overflow)?;
+            for (to, _) in &transfers {
+                self.ensure_transferable(&sender, to, total)?;
+            }
+            for (to, value) in transfers {
+                self.transfer_helper(&sender, &to, value)?;
+            }
+            Ok(())
+        }
 
-            self.env().emit_event(Approval {
+        /// Transfers `value` to `to` like `transfer` and emits `envelope`, an
+        /// opaque encrypted travel-rule payload of at most
+        /// `MAX_ENVELOPE_LEN` bytes, in a `TravelRuleEnvelope` event. The
+        /// contract never stores the envelope.
+        #[ink(message, selector = 0x1DE1A03B)]
+        pub fn transfer_with_envelope(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            envelope: Vec<u8>,
+        ) -> Result<()> {
+            self.trace_call();
+            if envelope.len() > MAX_ENVELOPE_LEN {
+                return Err(Error::EnvelopeTooLarge);
+            }
+            let sender = self.env().caller();
+            self.transfer_helper(&sender, &to, value)?;
+            self.env().emit_event(TravelRuleEnvelope {
                 from: sender,
-                to: spender,
+                to,
                 value,
+                envelope,
             });
-
             Ok(())
         }
 
-        pub fn transfer_helper(
+        /// Transfers `value` to `to` and, if `to` is a contract, calls its
+        /// `PSP22Receiver::on_received(operator, from, value, data)` hook.
+        /// The transfer is reverted if the hook fails or returns an error.
+        #[ink(message, selector = 0xACD10E50)]
+        pub fn transfer_and_call(
             &mut self,
-            from: &AccountId,
-            to: &AccountId,
+            to: AccountId,
             value: Balance,
+            data: Vec<u8>,
         ) -> Result<()> {
-            let balance_from = self.balance_of(*from);
-            let balance_to = self.balance_of(*to);
-
-            if value > balance_from {
-                return Err(Error::BalanceTooLow);
+            self.trace_call();
+            let sender = self.env().caller();
+            self.transfer_helper(&sender, &to, value)?;
+            if self.env().is_contract(&to) {
+                Self::call_psp22(
+                    to,
+                    ExecutionInput::new(Selector::new(ink::selector_bytes!(
+                        "PSP22Receiver::on_received"
+                    )))
+                    .push_arg(sender)
+                    .push_arg(sender)
+                    .push_arg(value)
+                    .push_arg(data),
+                )
+                .map_err(|_| Error::ReceiverRejected)?;
             }
-            self.balances.insert(from, &(balance_from - value));
-            self.balances.insert(to, &(balance_to + value));
-
-            self.env().emit_event(Transfer {
-                from: Some(*from),
-                to: Some(*to),
-                value,
-            });
-
             Ok(())
         }
-    }
-
-    #[cfg(test)]
-    mod tests {
-        use super::*;
 
-        type Event = <Erc20 as ::ink::reflect::ContractEventBase>::Type;
+        #[ink(message, selector = 0x681266A0)]
+        pub fn approve(&mut self, spender: AccountId, value: Balance) -> Result<()> {
+            self.trace_call();
+            let sender = self.env().caller();
+            self.approve_helper(&sender, &spender, value)
+        }
 
-        #[ink::test]
-        fn constructor_works() {
-            let erc20 = Erc20::new(1000);
-            assert_eq!(erc20.total_supply(), 1000);
+        /// Raises the caller's allowance for `spender` by `delta`.
+        #[ink(message, selector = 0xF551D422)]
+        pub fn increase_allowance(&mut self, spender: AccountId, delta: Balance) -> Result<()> {
+            self.trace_call();
+            let sender = self.env().caller();
+            let allowance = self
+                .allowance(sender, spender)
+                .checked_add(delta)
+                .ok_or(Error::Overflow)?;
+            self.approve_helper(&sender, &spender, allowance)
+        }
 
+        /// Lowers the caller's allowance for `spender` by `delta`, failing
+        /// rather than going below zero.
+        #[ink(message, selector = 0xF998EBD9)]
+        pub fn decrease_allowance(&mut self, spender: AccountId, delta: Balance) -> Result<()> {
+            self.trace_call();
+            let sender = self.env().caller();
+            let allowance = self
+                .allowance(sender, spender)
+                .checked_sub(delta)
+                .ok_or(Error::AllowanceToolow)?;
+            self.approve_helper(&sender, &spender, allowance)
+        }
+
+        /// Sets the allowance of `spender` over `owner`'s tokens to `value`,
+        /// authorised by `owner`'s ECDSA signature instead of a call from
+        /// `owner`, so a relayer can submit it and pay the fee.
+        ///
+        /// The signature covers the domain separator, owner, spender, value,
+        /// the owner's current nonce and `deadline`, a timestamp after which
+        /// the permit is no longer accepted.
+        #[ink(message, selector = 0x84AFF499)]
+        pub fn permit(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+            deadline: Timestamp,
+            signature: [u8; 65],
+        ) -> Result<()> {
+            self.trace_call();
+            self.use_permit(owner, spender, value, deadline, &signature)?;
+            self.approve_helper(&owner, &spender, value)
+        }
+
+        /// Transfers `value` from `owner` to `to` on the strength of a permit
+        /// `owner` signed for the caller, as `permit` would accept it. The
+        /// permit authorises this transfer only: no allowance is left behind
+        /// and any existing allowance is untouched. Like `permit`, it honours
+        /// `contract_spenders_only`.
+        #[ink(message, selector = 0xC53189E3)]
+        pub fn transfer_with_permit(
+            &mut self,
+            owner: AccountId,
+            to: AccountId,
+            value: Balance,
+            deadline: Timestamp,
+            signature: [u8; 65],
+        ) -> Result<()> {
+            self.trace_call();
+            let sender = self.env().caller();
+            self.ensure_spender_allowed(&owner, &sender)?;
+            self.use_permit(owner, sender, value, deadline, &signature)?;
+            self.transfer_helper(&owner, &to, value)
+        }
+
+        /// Sets the only account allowed to call `execute_meta_tx`, or
+        /// disables meta-transactions with `None`. Only the owner may call it.
+        #[ink(message, selector = 0xD41D7E38)]
+        pub fn set_trusted_forwarder(&mut self, forwarder: Option<AccountId>) -> Result<()> {
+            self.trace_call();
+            self.ensure_admin()?;
+            self.trusted_forwarder = forwarder;
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x91C55059)]
+        pub fn trusted_forwarder(&self) -> Option<AccountId> {
+            self.trusted_forwarder
+        }
+
+        /// Sets the chain extension function called with
+        /// `(from, to, value)` after every transfer, mint and burn, or stops
+        /// the notifications with `None`. Only the owner may call it.
+        ///
+        /// The extension's status code is ignored, so a failed notification
+        /// never blocks a transfer. The extension must report failures that
+        /// way rather than by aborting the call.
+        #[ink(message, selector = 0x6A51792C)]
+        pub fn set_transfer_extension(&mut self, func_id: Option<u32>) -> Result<()> {
+            self.trace_call();
+            self.ensure_admin()?;
+            self.transfer_extension = func_id;
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x63B62CB9)]
+        pub fn transfer_extension(&self) -> Option<u32> {
+            self.transfer_extension
+        }
+
+        /// Sets the share of every transfer paid to the fee collector, in
+        /// basis points, up to `BPS_DENOMINATOR`. Only the owner may call it.
+        #[ink(message, selector = 0xD3F73E00)]
+        pub fn set_fee_bps(&mut self, fee_bps: u16) -> Result<()> {
+            self.trace_call();
+            self.ensure_admin()?;
+            self.set_fee_bps_helper(fee_bps, self.env().caller())
+        }
+
+        /// Sets the account transfer fees are paid to, or stops charging
+        /// fees with `None`. Only the owner may call it.
+        #[ink(message, selector = 0xC5B70D50)]
+        pub fn set_fee_collector(&mut self, collector: Option<AccountId>) -> Result<()> {
+            self.trace_call();
+            self.ensure_admin()?;
+            self.set_fee_collector_helper(collector, self.env().caller());
+            Ok(())
+        }
+
+        /// Transfer fee in basis points.
+        #[ink(message, selector = 0xC374761C)]
+        pub fn transfer_fee(&self) -> u16 {
+            self.fee_bps
+        }
+
+        #[ink(message, selector = 0x04C1D059)]
+        pub fn fee_collector(&self) -> Option<AccountId> {
+            self.fee_collector
+        }
+
+        /// Sets the share of every transfer that is burned, in basis points,
+        /// up to `MAX_BURN_BPS`. Burning applies with or without a fee
+        /// collector, but the two together may not exceed the whole
+        /// transfer. Only the owner may call it.
+        #[ink(message, selector = 0xA4FF7BEF)]
+        pub fn set_burn_bps(&mut self, burn_bps: u16) -> Result<()> {
+            self.trace_call();
+            self.ensure_admin()?;
+            self.set_burn_bps_helper(burn_bps, self.env().caller())
+        }
+
+        /// Burn rate on transfers in basis points.
+        #[ink(message, selector = 0x1FB21571)]
+        pub fn burn_bps(&self) -> u16 {
+            self.burn_bps
+        }
+
+        /// Total transfer fees `account` has paid.
+        #[ink(message, selector = 0x3952B95B)]
+        pub fn fees_paid(&self, account: AccountId) -> Balance {
+            self.fees_paid.get(account).unwrap_or_default()
+        }
+
+        /// Total burned from `account`, by `burn`, `burn_from` or the burn
+        /// rate on its transfers.
+        #[ink(message, selector = 0x355A81E1)]
+        pub fn burned_by(&self, account: AccountId) -> Balance {
+            self.burned.get(account).unwrap_or_default()
+        }
+
+        /// Expands or contracts the supply by `supply_delta`, scaling every
+        /// balance in proportion. Holdings are stored as shares, which a
+        /// rebase leaves untouched. A `REBASER` may call it at any time, the
+        /// owner as an admin operation; the supply must stay within the cap
+        /// and above zero. Vesting grants, locks of this token and genesis
+        /// allocations are held as shares and scale with everything else.
+        #[ink(message, selector = 0x9ACE6980)]
+        pub fn rebase(&mut self, supply_delta: i128) -> Result<()> {
+            self.trace_call();
+            let caller = self.env().caller();
+            if !self.has_role(REBASER, caller) {
+                if self.owner != Some(caller) {
+                    return Err(Error::NotRebaser);
+                }
+                self.ensure_admin()?;
+            }
+            self.rebase_helper(supply_delta)
+        }
+
+        fn rebase_helper(&mut self, supply_delta: i128) -> Result<()> {
+            let previous_supply = self.total_supply;
+            if previous_supply == 0 {
+                return Err(Error::InvalidRebase);
+            }
+            let total_supply = if supply_delta < 0 {
+                previous_supply
+                    .checked_sub(supply_delta.unsigned_abs())
+                    .ok_or(Error::Underflow)?
+            } else {
+                previous_supply
+                    .checked_add(supply_delta.unsigned_abs())
+                    .ok_or(Error::Overflow)?
+            };
+            if total_supply == 0 {
+                return Err(Error::InvalidRebase);
+            }
+            Self::check_cap(total_supply, self.cap)?;
+            self.checkpoint_supply();
+            self.total_supply = total_supply;
+            self.env().emit_event(Rebased {
+                caller: self.env().caller(),
+                previous_supply,
+                total_supply,
+            });
+            Ok(())
+        }
+
+        /// Shares held by `account`; its balance is the same fraction of the
+        /// total supply as its shares are of `total_shares`.
+        #[ink(message, selector = 0xC0086DF1)]
+        pub fn shares_of(&self, account: AccountId) -> Balance {
+            self.balances.get(account).unwrap_or_default()
+        }
+
+        #[ink(message, selector = 0xACA19D26)]
+        pub fn total_shares(&self) -> Balance {
+            self.total_shares
+        }
+
+        /// Takes a snapshot of every balance and the total supply, readable
+        /// later through `balance_of_at` and `total_supply_at`. Returns its
+        /// id. Only a `SNAPSHOTTER` may call it.
+        ///
+        /// Nothing is copied here: balances are recorded lazily, the first
+        /// time they change after the snapshot.
+        #[ink(message, selector = 0x798ADA01)]
+        pub fn snapshot(&mut self) -> Result<SnapshotId> {
+            self.trace_call();
+            if !self.has_role(SNAPSHOTTER, self.env().caller()) {
+                return Err(Error::NotSnapshotter);
+            }
+            let id = self
+                .current_snapshot
+                .checked_add(1)
+                .ok_or(Error::Overflow)?;
+            self.current_snapshot = id;
+            self.env().emit_event(Snapshot {
+                caller: self.env().caller(),
+                id,
+            });
+            Ok(id)
+        }
+
+        /// Id of the latest snapshot, zero before the first one.
+        #[ink(message, selector = 0x7D4B55A1)]
+        pub fn current_snapshot(&self) -> SnapshotId {
+            self.current_snapshot
+        }
+
+        /// Balance of `account` when snapshot `snapshot_id` was taken.
+        #[ink(message, selector = 0x5A2F8344)]
+        pub fn balance_of_at(
+            &self,
+            account: AccountId,
+            snapshot_id: SnapshotId,
+        ) -> Result<Balance> {
+            self.ensure_snapshot_exists(snapshot_id)?;
+            let count = self.share_checkpoint_count.get(account).unwrap_or_default();
+            let checkpoint_id = |position| {
+                self.share_checkpoints
+                    .get((account, position))
+                    .map_or(0, |(id, _)| id)
+            };
+            let position = Self::find_checkpoint(count, snapshot_id, checkpoint_id);
+            let shares = self
+                .share_checkpoints
+                .get((account, position))
+                .map_or_else(|| self.shares_of(account), |(_, shares)| shares);
+            let (total_supply, total_shares) = self.supply_at(snapshot_id);
+            Ok(Self::shares_to_balance(shares, total_supply, total_shares))
+        }
+
+        /// Total supply when snapshot `snapshot_id` was taken.
+        #[ink(message, selector = 0x3727369D)]
+        pub fn total_supply_at(&self, snapshot_id: SnapshotId) -> Result<Balance> {
+            self.ensure_snapshot_exists(snapshot_id)?;
+            Ok(self.supply_at(snapshot_id).0)
+        }
+
+        /// Delegates the caller's voting power, present and future, to
+        /// `to`; holders who want to vote themselves delegate to their own
+        /// account. Tokens count as votes only once delegated.
+        ///
+        /// Voting power is measured in shares, which equal balances until
+        /// the first rebase. A rebase scales every holder alike, so it does
+        /// not change anyone's share of the vote.
+        #[ink(message, selector = 0xC59654FE)]
+        pub fn delegate(&mut self, to: AccountId) -> Result<()> {
+            self.trace_call();
+            let delegator = self.env().caller();
+            let from_delegate = self.delegates(delegator);
+            self.delegates.insert(delegator, &to);
+            self.env().emit_event(DelegateChanged {
+                delegator,
+                from_delegate,
+                to_delegate: Some(to),
+            });
+            self.move_votes(from_delegate, Some(to), self.shares_of(delegator));
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x804C53D9)]
+        pub fn delegates(&self, account: AccountId) -> Option<AccountId> {
+            self.delegates.get(account)
+        }
+
+        /// Current voting power of `account`.
+        #[ink(message, selector = 0x5F9D374C)]
+        pub fn get_votes(&self, account: AccountId) -> Balance {
+            let count = self.vote_checkpoint_count.get(account).unwrap_or_default();
+            count
+                .checked_sub(1)
+                .and_then(|last| self.vote_checkpoints.get((account, last)))
+                .map_or(0, |(_, votes)| votes)
+        }
+
+        /// Voting power of `account` at the end of `block`, which must
+        /// already be finished.
+        #[ink(message, selector = 0x7995D5FC)]
+        pub fn get_past_votes(&self, account: AccountId, block: BlockNumber) -> Result<Balance> {
+            if block >= self.env().block_number() {
+                return Err(Error::FutureLookup);
+            }
+            let count = self.vote_checkpoint_count.get(account).unwrap_or_default();
+            let checkpoint_block = |position| {
+                self.vote_checkpoints
+                    .get((account, position))
+                    .map_or(0, |(block, _)| block)
+            };
+            // The last checkpoint at or before `block` is the one before the
+            // first checkpoint after it.
+            Ok(Self::find_checkpoint(count, block + 1, checkpoint_block)
+                .checked_sub(1)
+                .and_then(|position| self.vote_checkpoints.get((account, position)))
+                .map_or(0, |(_, votes)| votes))
+        }
+
+        /// Exempts transfers from and to `account` from the transfer fee.
+        /// Only the owner may call it.
+        #[ink(message, selector = 0x91F18B9D)]
+        pub fn add_fee_exempt(&mut self, account: AccountId) -> Result<()> {
+            self.trace_call();
+            self.ensure_admin()?;
+            self.set_fee_exempt_helper(account, true, self.env().caller());
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x4437F826)]
+        pub fn remove_fee_exempt(&mut self, account: AccountId) -> Result<()> {
+            self.trace_call();
+            self.ensure_admin()?;
+            self.set_fee_exempt_helper(account, false, self.env().caller());
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x77D423C1)]
+        pub fn is_fee_exempt(&self, account: AccountId) -> bool {
+            self.fee_exempt.contains(account)
+        }
+
+        /// Relays a transfer of `value` from `from` to `to` that `from`
+        /// signed off-chain. The effective sender is `from`, as proven by
+        /// `signature` over the domain separator, recipient, value, the
+        /// sender's nonce and `deadline`; the caller must be the trusted
+        /// forwarder. Shares nonces with `permit`.
+        #[ink(message, selector = 0x58158CF9)]
+        pub fn execute_meta_tx(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            deadline: Timestamp,
+            signature: [u8; 65],
+        ) -> Result<()> {
+            self.trace_call();
+            if self.trusted_forwarder != Some(self.env().caller()) {
+                return Err(Error::NotTrustedForwarder);
+            }
+            let hash = self.meta_tx_hash(from, to, value, self.nonces(from), deadline);
+            self.use_signature(from, deadline, &hash, &signature)?;
+            self.transfer_helper(&from, &to, value)
+        }
+
+        /// Nonce the next permit or meta-transaction signed by `owner` must
+        /// carry.
+        #[ink(message, selector = 0x7D7E596C)]
+        pub fn nonces(&self, owner: AccountId) -> u64 {
+            self.nonces.get(owner).unwrap_or_default()
+        }
+
+        /// Binds permits to this chain, contract and deployment, so a
+        /// signature cannot be replayed against another token or chain.
+        #[ink(message, selector = 0xB7F73B4A)]
+        pub fn domain_separator(&self) -> Hash {
+            let mut separator = <Blake2x256 as HashOutput>::Type::default();
+            ink::env::hash_encoded::<Blake2x256, _>(
+                &(
+                    b"Erc20::permit",
+                    self.chain_id,
+                    self.env().account_id(),
+                    self.deployment_id,
+                ),
+                &mut separator,
+            );
+            separator.into()
+        }
+
+        /// Creates `value` new tokens for `to`. Only minters may call it.
+        #[ink(message, selector = 0xCFDD9AA2)]
+        pub fn mint(&mut self, to: AccountId, value: Balance) -> Result<()> {
+            self.trace_call();
+            self.ensure_minter()?;
+            self.mint_helper(&to, value)
+        }
+
+        /// Starts an airdrop of the `(index, account, amount)` leaves committed
+        /// to by `root`. Replacing the root starts a new airdrop with no
+        /// claims. Only the owner may call it.
+        #[ink(message, selector = 0xC4671281)]
+        pub fn set_merkle_root(&mut self, root: Hash) -> Result<()> {
+            self.trace_call();
+            self.ensure_admin()?;
+            self.merkle_root = Some(root);
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x7EDF9E0E)]
+        pub fn merkle_root(&self) -> Option<Hash> {
+            self.merkle_root
+        }
+
+        /// Mints `amount` to the caller if leaf `index` of the current airdrop
+        /// assigns it to them, as shown by `proof`: the sibling hashes from
+        /// the leaf up to the root. Each leaf can be claimed once.
+        #[ink(message, selector = 0xB388803F)]
+        pub fn claim(&mut self, index: u32, amount: Balance, proof: Vec<Hash>) -> Result<()> {
+            self.trace_call();
+            let root = self.merkle_root.ok_or(Error::InvalidProof)?;
+            if self.is_claimed(index) {
+                return Err(Error::AlreadyClaimed);
+            }
+            let account = self.env().caller();
+            let node = proof.iter().fold(
+                Self::merkle_leaf(index, account, amount),
+                |node, sibling| Self::merkle_parent(&node, sibling),
+            );
+            if node != root {
+                return Err(Error::InvalidProof);
+            }
+
+            self.mint_helper(&account, amount)?;
+            let word = (root, index / 128);
+            let claimed = self.claimed.get(word).unwrap_or_default() | (1 << (index % 128));
+            self.claimed.insert(word, &claimed);
+            self.env().emit_event(Claimed {
+                account,
+                index,
+                amount,
+            });
+            Ok(())
+        }
+
+        /// Whether leaf `index` of the current airdrop has been claimed.
+        #[ink(message, selector = 0xC58EED46)]
+        pub fn is_claimed(&self, index: u32) -> bool {
+            self.merkle_root.is_some_and(|root| {
+                self.claimed.get((root, index / 128)).unwrap_or_default() & (1 << (index % 128))
+                    != 0
+            })
+        }
+
+        /// Moves `total` of the caller's tokens into the contract, vesting to
+        /// `beneficiary` linearly over `duration` from `start` once `cliff`
+        /// has passed. A beneficiary holds at most one grant. Only the owner
+        /// may call it.
+        #[ink(message, selector = 0xB471202D)]
+        pub fn create_vesting(
+            &mut self,
+            beneficiary: AccountId,
+            total: Balance,
+            start: Timestamp,
+            cliff: Timestamp,
+            duration: Timestamp,
+        ) -> Result<()> {
+            self.trace_call();
+            self.ensure_owner()?;
+            if duration == 0 || cliff > duration || start.checked_add(duration).is_none() {
+                return Err(Error::InvalidSchedule);
+            }
+            if self.vesting_grants.contains(beneficiary) {
+                return Err(Error::GrantExists);
+            }
+            let shares = self.to_shares(total)?;
+            let owner = self.env().caller();
+            let vault = self.env().account_id();
+            self.transfer_helper(&owner, &vault, total)?;
+            self.vesting_grants.insert(
+                beneficiary,
+                &VestingGrant {
+                    total: shares,
+                    released: 0,
+                    start,
+                    cliff,
+                    duration,
+                },
+            );
+            self.env().emit_event(VestingCreated {
+                caller: owner,
+                beneficiary,
+                total,
+                start,
+                cliff,
+                duration,
+            });
+            Ok(())
+        }
+
+        /// Pays the caller everything vested but not yet released, returning
+        /// the amount paid.
+        #[ink(message, selector = 0x3F2BE152)]
+        pub fn release(&mut self) -> Result<Balance> {
+            self.trace_call();
+            let beneficiary = self.env().caller();
+            let mut grant = self.vesting_grants.get(beneficiary).ok_or(Error::NoGrant)?;
+            let shares = Self::vested_shares(&grant, self.env().block_timestamp())
+                .saturating_sub(grant.released);
+            if shares == 0 {
+                return Ok(0);
+            }
+            let amount = self.to_balance(shares);
+            grant.released = grant.released.checked_add(shares).ok_or(Error::Overflow)?;
+            self.vesting_grants.insert(beneficiary, &grant);
+            let vault = self.env().account_id();
+            self.transfer_shares_helper(&vault, &beneficiary, amount, shares)?;
+            self.env().emit_event(TokensReleased {
+                beneficiary,
+                amount,
+            });
+            Ok(amount)
+        }
+
+        /// Part of `beneficiary`'s grant vested at time `at`, released or not.
+        #[ink(message, selector = 0x978A3F5A)]
+        pub fn vested_amount(&self, beneficiary: AccountId, at: Timestamp) -> Balance {
+            self.vesting_grants
+                .get(beneficiary)
+                .map_or(0, |grant| self.to_balance(Self::vested_shares(&grant, at)))
+        }
+
+        /// What `release` would pay `beneficiary` now.
+        #[ink(message, selector = 0xE0875381)]
+        pub fn releasable(&self, beneficiary: AccountId) -> Balance {
+            self.vesting_grants.get(beneficiary).map_or(0, |grant| {
+                let vested = Self::vested_shares(&grant, self.env().block_timestamp());
+                self.to_balance(vested.saturating_sub(grant.released))
+            })
+        }
+
+        /// Shares of `grant` vested at time `at`, released or not.
+        fn vested_shares(grant: &VestingGrant, at: Timestamp) -> Balance {
+            let elapsed = at.saturating_sub(grant.start);
+            if elapsed < grant.cliff {
+                return 0;
+            }
+            if elapsed >= grant.duration {
+                return grant.total;
+            }
+            let (elapsed, duration) = (Balance::from(elapsed), Balance::from(grant.duration));
+            Self::mul_div(grant.total, elapsed, duration).unwrap_or(grant.total)
+        }
+
+        #[ink(message, selector = 0xB27C6226)]
+        pub fn vesting_grant(&self, beneficiary: AccountId) -> Option<VestingGrant> {
+            self.vesting_grants.get(beneficiary)
+        }
+
+        #[ink(message, selector = 0xFEAEA4FA)]
+        pub fn owner(&self) -> Option<AccountId> {
+            self.owner
+        }
+
+        #[ink(message, selector = 0x6E429D8D)]
+        pub fn pending_owner(&self) -> Option<AccountId> {
+            self.pending_owner
+        }
+
+        /// Nominates `new_owner`, who becomes owner once they call
+        /// `accept_ownership`. A later nomination replaces this one.
+        #[ink(message, selector = 0x107E33EA)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<()> {
+            self.trace_call();
+            self.ensure_owner()?;
+            self.pending_owner = Some(new_owner);
+            Ok(())
+        }
+
+        #[ink(message, selector = 0xB55BE9F0)]
+        pub fn accept_ownership(&mut self) -> Result<()> {
+            self.trace_call();
+            let caller = self.env().caller();
+            if self.pending_owner != Some(caller) {
+                return Err(Error::NotPendingOwner);
+            }
+            self.pending_owner = None;
+            self.set_owner(Some(caller));
+            Ok(())
+        }
+
+        /// Gives up ownership for good, disabling every owner-only message.
+        #[ink(message, selector = 0x8C90065B)]
+        pub fn renounce_ownership(&mut self) -> Result<()> {
+            self.trace_call();
+            self.ensure_owner()?;
+            self.pending_owner = None;
+            self.set_owner(None);
+            Ok(())
+        }
+
+        /// Destroys `value` of the caller's tokens.
+        #[ink(message, selector = 0xB1EFC17B)]
+        pub fn burn(&mut self, value: Balance) -> Result<()> {
+            self.trace_call();
+            let sender = self.env().caller();
+            self.burn_helper(&sender, value)
+        }
+
+        /// Destroys `value` of `from`'s tokens, spending the caller's
+        /// allowance.
+        #[ink(message, selector = 0x27212BBB)]
+        pub fn burn_from(&mut self, from: AccountId, value: Balance) -> Result<()> {
+            self.trace_call();
+            let sender = self.env().caller();
+            self.spend_allowance(&from, &sender, value)?;
+            self.burn_helper(&from, value)
+        }
+
+        /// Lets `account` mint. Shorthand for granting `MINTER`.
+        #[ink(message, selector = 0xE55FB130)]
+        pub fn add_minter(&mut self, account: AccountId) -> Result<()> {
+            self.grant_role(MINTER, account)
+        }
+
+        /// Revokes minting from `account`. Shorthand for revoking `MINTER`.
+        #[ink(message, selector = 0xE4873304)]
+        pub fn remove_minter(&mut self, account: AccountId) -> Result<()> {
+            self.revoke_role(MINTER, account)
+        }
+
+        #[ink(message, selector = 0x13FC1F2D)]
+        pub fn is_minter(&self, account: AccountId) -> bool {
+            self.has_role(MINTER, account)
+        }
+
+        /// Gives `role` to `account`. Only the owner may call it.
+        #[ink(message, selector = 0x2AABFAB5)]
+        pub fn grant_role(&mut self, role: RoleId, account: AccountId) -> Result<()> {
+            self.trace_call();
+            self.ensure_admin()?;
+            self.grant_role_helper(role, account, self.env().caller());
+            Ok(())
+        }
+
+        /// Takes `role` away from `account`. Only the owner may call it.
+        #[ink(message, selector = 0x35E1EF4A)]
+        pub fn revoke_role(&mut self, role: RoleId, account: AccountId) -> Result<()> {
+            self.trace_call();
+            self.ensure_admin()?;
+            self.revoke_role_helper(role, account, self.env().caller());
+            Ok(())
+        }
+
+        /// Queues `operation` to become executable `min_delay` blocks from
+        /// now, returning its id: the hash of `operation` and `salt`. A salt
+        /// lets the same operation be queued more than once. Only the owner
+        /// may call it.
+        ///
+        /// Once `min_delay` is non-zero, the admin messages that
+        /// `AdminOperation` covers can only run this way, and their events
+        /// name the owner who scheduled them rather than whoever executed.
+        #[ink(message, selector = 0x9DB83196)]
+        pub fn schedule(&mut self, operation: AdminOperation, salt: Hash) -> Result<Hash> {
+            self.trace_call();
+            self.ensure_owner()?;
+            let id = Self::operation_id(&operation, salt);
+            if self.scheduled_operations.contains(id) {
+                return Err(Error::OperationExists);
+            }
+            let ready_at = self
+                .env()
+                .block_number()
+                .checked_add(self.min_delay)
+                .ok_or(Error::Overflow)?;
+            self.scheduled_operations
+                .insert(id, &(ready_at, self.env().caller()));
+            self.env().emit_event(OperationScheduled {
+                caller: self.env().caller(),
+                id,
+                operation,
+                ready_at,
+            });
+            Ok(id)
+        }
+
+        /// Runs a scheduled operation once its delay has passed. Anyone may
+        /// call it, but only while the owner who scheduled it still owns the
+        /// contract, so renouncing or handing over ownership retires the
+        /// queue.
+        #[ink(message, selector = 0xD00EC894)]
+        pub fn execute(&mut self, operation: AdminOperation, salt: Hash) -> Result<()> {
+            self.trace_call();
+            let id = Self::operation_id(&operation, salt);
+            let (ready_at, scheduler) = self
+                .scheduled_operations
+                .get(id)
+                .ok_or(Error::UnknownOperation)?;
+            if self.env().block_number() < ready_at {
+                return Err(Error::OperationNotReady);
+            }
+            if self.owner != Some(scheduler) {
+                return Err(Error::NotOwner);
+            }
+            self.scheduled_operations.remove(id);
+            self.apply_operation(operation, scheduler)?;
+            self.env().emit_event(OperationExecuted {
+                caller: self.env().caller(),
+                id,
+            });
+            Ok(())
+        }
+
+        /// Drops a scheduled operation before it runs. Only the owner may
+        /// call it.
+        #[ink(message, selector = 0x9796E9A7)]
+        pub fn cancel(&mut self, id: Hash) -> Result<()> {
+            self.trace_call();
+            self.ensure_owner()?;
+            if self.scheduled_operations.take(id).is_none() {
+                return Err(Error::UnknownOperation);
+            }
+            self.env().emit_event(OperationCancelled {
+                caller: self.env().caller(),
+                id,
+            });
+            Ok(())
+        }
+
+        /// Block from which scheduled operation `id` can be executed.
+        #[ink(message, selector = 0x244B6C08)]
+        pub fn operation_ready_at(&self, id: Hash) -> Option<BlockNumber> {
+            self.scheduled_operations
+                .get(id)
+                .map(|(ready_at, _)| ready_at)
+        }
+
+        /// Blocks an admin operation must wait between `schedule` and
+        /// `execute`. Zero disables the timelock.
+        #[ink(message, selector = 0xB99947D1)]
+        pub fn min_delay(&self) -> BlockNumber {
+            self.min_delay
+        }
+
+        /// Sets `min_delay`. Only the owner may call it; once a delay is set,
+        /// changing it is itself delayed.
+        #[ink(message, selector = 0x5517ECE1)]
+        pub fn set_min_delay(&mut self, delay: BlockNumber) -> Result<()> {
+            self.trace_call();
+            self.ensure_admin()?;
+            self.min_delay = delay;
+            Ok(())
+        }
+
+        /// Gives up `role` held by the caller.
+        #[ink(message, selector = 0xFAB8657B)]
+        pub fn renounce_role(&mut self, role: RoleId) -> Result<()> {
+            self.trace_call();
+            let sender = self.env().caller();
+            self.revoke_role_helper(role, sender, sender);
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x8D194A68)]
+        pub fn has_role(&self, role: RoleId, account: AccountId) -> bool {
+            self.roles.contains((role, account))
+        }
+
+        /// Halts transfers, approvals, minting and burning. Only `PAUSER`
+        /// holders may call it.
+        #[ink(message, selector = 0x81E0C604)]
+        pub fn pause(&mut self) -> Result<()> {
+            self.trace_call();
+            self.ensure_pauser()?;
+            if !self.paused {
+                self.paused = true;
+                self.env().emit_event(Paused {
+                    account: self.env().caller(),
+                });
+            }
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x67616649)]
+        pub fn unpause(&mut self) -> Result<()> {
+            self.trace_call();
+            self.ensure_pauser()?;
+            if self.paused {
+                self.paused = false;
+                self.env().emit_event(Unpaused {
+                    account: self.env().caller(),
+                });
+            }
+            Ok(())
+        }
+
+        #[ink(message, selector = 0xD8FEA916)]
+        pub fn paused(&self) -> bool {
+            self.paused
+        }
+
+        /// Deposits `amount` of the PSP22 `token` into the locker until
+        /// `unlock_at`, returning the lock id. The locker must have been
+        /// approved to spend `amount` of `token` beforehand.
+        #[ink(message, selector = 0x567C1EAE)]
+        pub fn lock_tokens(
+            &mut self,
+            token: AccountId,
+            amount: Balance,
+            unlock_at: Timestamp,
+        ) -> Result<u32> {
+            self.trace_call();
+            if unlock_at <= self.env().block_timestamp() {
+                return Err(Error::InvalidUnlockTime);
+            }
+            let owner = self.env().caller();
+            let locker = self.env().account_id();
+            let locked = if token == locker {
+                self.to_shares(amount)?
+            } else {
+                amount
+            };
+            let total_locked = self
+                .total_locked
+                .get(token)
+                .unwrap_or_default()
+                .checked_add(locked)
+                .ok_or(Error::Overflow)?;
+
+            if token == locker {
+                self.transfer_helper(&owner, &locker, amount)?;
+            } else {
+                Self::call_psp22(
+                    token,
+                    ExecutionInput::new(Selector::new(ink::selector_bytes!(
+                        "PSP22::transfer_from"
+                    )))
+                    .push_arg(owner)
+                    .push_arg(locker)
+                    .push_arg(amount)
+                    .push_arg(Vec::<u8>::new()),
+                )?;
+            }
+
+            let id = self.next_lock_id;
+            self.next_lock_id = id.checked_add(1).ok_or(Error::Overflow)?;
+            self.token_locks.insert(
+                id,
+                &TokenLock {
+                    token,
+                    owner,
+                    amount: locked,
+                    unlock_at,
+                },
+            );
+            self.total_locked.insert(token, &total_locked);
+            self.env().emit_event(TokensLocked {
+                owner,
+                token,
+                id,
+                amount,
+                unlock_at,
+            });
+
+            Ok(id)
+        }
+
+        /// Returns the tokens of lock `id` to its owner once it has unlocked.
+        #[ink(message, selector = 0x4BC9BB48)]
+        pub fn withdraw_lock(&mut self, id: u32) -> Result<()> {
+            self.trace_call();
+            let lock = self.token_locks.get(id).ok_or(Error::UnknownLock)?;
+            if lock.owner != self.env().caller() {
+                return Err(Error::NotLockOwner);
+            }
+            if self.env().block_timestamp() < lock.unlock_at {
+                return Err(Error::StillLocked);
+            }
+            let total_locked = self
+                .total_locked
+                .get(lock.token)
+                .unwrap_or_default()
+                .checked_sub(lock.amount)
+                .ok_or(Error::Underflow)?;
+            self.token_locks.remove(id);
+            self.total_locked.insert(lock.token, &total_locked);
+
+            let locker = self.env().account_id();
+            let amount = if lock.token == locker {
+                let amount = self.to_balance(lock.amount);
+                self.transfer_shares_helper(&locker, &lock.owner, amount, lock.amount)?;
+                amount
+            } else {
+                Self::call_psp22(
+                    lock.token,
+                    ExecutionInput::new(Selector::new(ink::selector_bytes!("PSP22::transfer")))
+                        .push_arg(lock.owner)
+                        .push_arg(lock.amount)
+                        .push_arg(Vec::<u8>::new()),
+                )?;
+                lock.amount
+            };
+            self.env().emit_event(LockWithdrawn {
+                owner: lock.owner,
+                token: lock.token,
+                id,
+                amount,
+            });
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x5FEB3C92)]
+        pub fn token_lock(&self, id: u32) -> Option<TokenLock> {
+            let mut lock = self.token_locks.get(id)?;
+            if lock.token == self.env().account_id() {
+                lock.amount = self.to_balance(lock.amount);
+            }
+            Some(lock)
+        }
+
+        /// Amount of `token` currently held by the locker, so anyone can
+        /// verify how much liquidity is locked.
+        #[ink(message, selector = 0x68254F77)]
+        pub fn total_locked(&self, token: AccountId) -> Balance {
+            let locked = self.total_locked.get(token).unwrap_or_default();
+            if token == self.env().account_id() {
+                return self.to_balance(locked);
+            }
+            locked
+        }
+
+        /// Approvals to accounts without contract code, a common
+        /// approval-phishing pattern, are rejected by default. Owners who
+        /// need them opt out with `enabled = false`. Only raising an
+        /// allowance is checked.
+        #[ink(message, selector = 0x12561DF8)]
+        pub fn set_contract_spenders_only(&mut self, enabled: bool) {
+            self.trace_call();
+            let sender = self.env().caller();
+            if enabled {
+                self.allows_plain_spenders.remove(sender);
+            } else {
+                self.allows_plain_spenders.insert(sender, &());
+            }
+        }
+
+        #[ink(message, selector = 0x4A8BDF58)]
+        pub fn contract_spenders_only(&self, owner: AccountId) -> bool {
+            !self.allows_plain_spenders.contains(owner)
+        }
+
+        /// Makes transfers, mints and airdrop claims to the caller fail with
+        /// `Error::RecipientRefused`, for custodial or retired addresses that
+        /// cannot process deposits.
+        #[ink(message, selector = 0x1A3F413F)]
+        pub fn set_refuse_incoming(&mut self, refuse: bool) {
+            self.trace_call();
+            let sender = self.env().caller();
+            if refuse {
+                self.refuses_incoming.insert(sender, &());
+            } else {
+                self.refuses_incoming.remove(sender);
+            }
+        }
+
+        #[ink(message, selector = 0x2DD0EC27)]
+        pub fn refuses_incoming(&self, account: AccountId) -> bool {
+            self.refuses_incoming.contains(account)
+        }
+
+        /// Registers `tag` as an alias of the caller, first come first served.
+        ///
+        /// Tags are 1 to `MAX_TAG_LEN` bytes of lowercase ASCII letters, digits,
+        /// `-` and `_`, which keeps look-alike tags from being registered.
+        #[ink(message, selector = 0x77BCAAB3)]
+        pub fn register_tag(&mut self, tag: String) -> Result<()> {
+            self.trace_call();
+            let valid = !tag.is_empty()
+                && tag.len() <= MAX_TAG_LEN
+                && tag.bytes().all(|b| {
+                    b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_'
+                });
+            if !valid {
+                return Err(Error::InvalidTag);
+            }
+            if self.tags.contains(&tag) {
+                return Err(Error::TagTaken);
+            }
+            self.tags.insert(&tag, &self.env().caller());
+            Ok(())
+        }
+
+        #[ink(message, selector = 0x0D48B4AA)]
+        pub fn resolve_tag(&self, tag: String) -> Option<AccountId> {
+            self.tags.get(&tag)
+        }
+
+        #[ink(message, selector = 0xE8C677A3)]
+        pub fn transfer_to_tag(&mut self, tag: String, value: Balance) -> Result<()> {
+            self.trace_call();
+            let to = self.tags.get(&tag).ok_or(Error::UnknownTag)?;
+            let sender = self.env().caller();
+            self.transfer_helper(&sender, &to, value)
+        }
+
+        /// Reports what a transfer of `value` from `from` to `to` would do,
+        /// so wallets can show the outcome before signing.
+        #[ink(message, selector = 0x01E3A5EB)]
+        pub fn simulate_transfer(
+            &self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> TransferSimulation {
+            let (fee, burned) = self.charges_for(&from, &to, value);
+            let fee = fee.map_or(0, |(_, fee)| fee);
+            TransferSimulation {
+                net: value - fee - burned,
+                fee,
+                burned,
+                blocked_by: self.ensure_transferable(&from, &to, value).err(),
+            }
+        }
+
+        pub fn transfer_helper(
+            &mut self,
+            from: &AccountId,
+            to: &AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            let shares = self.to_shares(value)?;
+            self.transfer_shares_helper(from, to, value, shares)
+        }
+
+        /// Moves `shares`, worth `value`, from `from` to `to`. Escrows held
+        /// as shares pay out through this directly, so converting back from
+        /// `value` cannot leave rounding dust with the contract.
+        fn transfer_shares_helper(
+            &mut self,
+            from: &AccountId,
+            to: &AccountId,
+            value: Balance,
+            shares: Balance,
+        ) -> Result<()> {
+            self.ensure_transferable(from, to, value)?;
+            let (fee, burned) = self.charges_for(from, to, value);
+            let net = value - fee.map_or(0, |(_, fee)| fee) - burned;
+
+            // Charges are converted on their own and the recipient gets the
+            // remaining shares, so rounding never creates or loses shares.
+            let burned_shares = self.to_shares(burned)?;
+            let fee_shares = match fee {
+                Some((collector, fee)) => Some((collector, self.to_shares(fee)?)),
+                None => None,
+            };
+            let net_shares = shares - fee_shares.map_or(0, |(_, shares)| shares) - burned_shares;
+
+            let shares_from = self
+                .shares_of(*from)
+                .checked_sub(shares)
+                .ok_or(Error::Underflow)?;
+            let total_supply = self
+                .total_supply
+                .checked_sub(burned)
+                .ok_or(Error::Underflow)?;
+            let total_shares = self
+                .total_shares
+                .checked_sub(burned_shares)
+                .ok_or(Error::Underflow)?;
+            // New shares are collected before any is written, so accounts
+            // that appear twice (a self-transfer, or the fee collector on
+            // either side) see the earlier update, and an error leaves
+            // storage untouched.
+            let mut updates = Vec::from([(*from, shares_from)]);
+            for (account, amount) in core::iter::once((*to, net_shares)).chain(fee_shares) {
+                match updates.iter_mut().find(|(updated, _)| *updated == account) {
+                    Some((_, shares)) => {
+                        *shares = shares.checked_add(amount).ok_or(Error::Overflow)?
+                    }
+                    None => updates.push((
+                        account,
+                        self.shares_of(account)
+                            .checked_add(amount)
+                            .ok_or(Error::Overflow)?,
+                    )),
+                }
+            }
+            for (account, shares) in updates {
+                self.checkpoint_shares(account);
+                self.balances.insert(account, &shares);
+            }
+            self.checkpoint_supply();
+            self.total_supply = total_supply;
+            self.total_shares = total_shares;
+            let from_delegate = self.delegates(*from);
+            self.move_votes(from_delegate, self.delegates(*to), net_shares);
+            if let Some((collector, fee_shares)) = fee_shares {
+                self.move_votes(from_delegate, self.delegates(collector), fee_shares);
+            }
+            self.move_votes(from_delegate, None, burned_shares);
+
+            self.emit_transfer(Some(*from), Some(*to), net);
+            if let Some((collector, fee)) = fee.filter(|(_, fee)| *fee > 0) {
+                // Like `record_burn`, the total saturates.
+                let fees_paid = self.fees_paid(*from).saturating_add(fee);
+                self.fees_paid.insert(from, &fees_paid);
+                self.emit_transfer(Some(*from), Some(collector), fee);
+            }
+            if burned > 0 {
+                self.record_burn(from, burned);
+                self.emit_transfer(Some(*from), None, burned);
+            }
+
+            Ok(())
+        }
+
+        fn ensure_owner(&self) -> Result<()> {
+            if self.owner != Some(self.env().caller()) {
+                return Err(Error::NotOwner);
+            }
+            Ok(())
+        }
+
+        /// Checks that the caller may run an admin operation directly: they
+        /// must be the owner, and no timelock delay may be set. Every message
+        /// with an `AdminOperation` counterpart calls this, so once
+        /// `min_delay` is non-zero they only run through `schedule`.
+        fn ensure_admin(&self) -> Result<()> {
+            self.ensure_owner()?;
+            if self.min_delay > 0 {
+                return Err(Error::Timelocked);
+            }
+            Ok(())
+        }
+
+        fn operation_id(operation: &AdminOperation, salt: Hash) -> Hash {
+            let mut id = <Blake2x256 as HashOutput>::Type::default();
+            ink::env::hash_encoded::<Blake2x256, _>(&(operation, salt), &mut id);
+            id.into()
+        }
+
+        /// Runs `operation` on behalf of `scheduler`, the owner who queued it.
+        fn apply_operation(
+            &mut self,
+            operation: AdminOperation,
+            scheduler: AccountId,
+        ) -> Result<()> {
+            match operation {
+                AdminOperation::GrantRole(role, account) => {
+                    self.grant_role_helper(role, account, scheduler)
+                }
+                AdminOperation::RevokeRole(role, account) => {
+                    self.revoke_role_helper(role, account, scheduler)
+                }
+                AdminOperation::SetTrustedForwarder(forwarder) => {
+                    self.trusted_forwarder = forwarder
+                }
+                AdminOperation::SetTransferExtension(func_id) => self.transfer_extension = func_id,
+                AdminOperation::SetMerkleRoot(root) => self.merkle_root = Some(root),
+                AdminOperation::SetMinDelay(delay) => self.min_delay = delay,
+                AdminOperation::SetFeeBps(fee_bps) => {
+                    return self.set_fee_bps_helper(fee_bps, scheduler)
+                }
+                AdminOperation::SetFeeCollector(collector) => {
+                    self.set_fee_collector_helper(collector, scheduler)
+                }
+                AdminOperation::AddFeeExempt(account) => {
+                    self.set_fee_exempt_helper(account, true, scheduler)
+                }
+                AdminOperation::RemoveFeeExempt(account) => {
+                    self.set_fee_exempt_helper(account, false, scheduler)
+                }
+                AdminOperation::SetBurnBps(burn_bps) => {
+                    return self.set_burn_bps_helper(burn_bps, scheduler)
+                }
+                AdminOperation::Rebase(supply_delta) => return self.rebase_helper(supply_delta),
+            }
+            Ok(())
+        }
+
+        fn set_fee_bps_helper(&mut self, fee_bps: u16, sender: AccountId) -> Result<()> {
+            if fee_bps > BPS_DENOMINATOR - self.burn_bps {
+                return Err(Error::InvalidFee);
+            }
+            self.fee_bps = fee_bps;
+            self.env().emit_event(FeeBpsChanged { sender, fee_bps });
+            Ok(())
+        }
+
+        fn set_fee_collector_helper(&mut self, collector: Option<AccountId>, sender: AccountId) {
+            self.fee_collector = collector;
+            self.env()
+                .emit_event(FeeCollectorChanged { sender, collector });
+        }
+
+        fn set_fee_exempt_helper(&mut self, account: AccountId, exempt: bool, sender: AccountId) {
+            if self.is_fee_exempt(account) == exempt {
+                return;
+            }
+            if exempt {
+                self.fee_exempt.insert(account, &());
+            } else {
+                self.fee_exempt.remove(account);
+            }
+            self.env().emit_event(FeeExemptionChanged {
+                sender,
+                account,
+                exempt,
+            });
+        }
+
+        fn set_burn_bps_helper(&mut self, burn_bps: u16, sender: AccountId) -> Result<()> {
+            if burn_bps > MAX_BURN_BPS || burn_bps > BPS_DENOMINATOR - self.fee_bps {
+                return Err(Error::InvalidFee);
+            }
+            self.burn_bps = burn_bps;
+            self.env().emit_event(BurnBpsChanged { sender, burn_bps });
+            Ok(())
+        }
+
+        /// Fee a transfer of `value` from `from` to `to` pays, with the
+        /// account it goes to, and the amount it burns. Moves into or out of
+        /// the contract's own account, such as locks and vesting, or a
+        /// fee-exempt account are not charged either.
+        fn charges_for(
+            &self,
+            from: &AccountId,
+            to: &AccountId,
+            value: Balance,
+        ) -> (Option<(AccountId, Balance)>, Balance) {
+            let contract = self.env().account_id();
+            if *from == contract
+                || *to == contract
+                || self.is_fee_exempt(*from)
+                || self.is_fee_exempt(*to)
+            {
+                return (None, 0);
+            }
+            let share = |bps: u16| {
+                Self::mul_div(value, Balance::from(bps), Balance::from(BPS_DENOMINATOR))
+                    .unwrap_or(value)
+            };
+            let fee = self
+                .fee_collector
+                .filter(|_| self.fee_bps > 0)
+                .map(|collector| (collector, share(self.fee_bps)));
+            (fee, share(self.burn_bps))
+        }
+
+        fn grant_role_helper(&mut self, role: RoleId, account: AccountId, sender: AccountId) {
+            if !self.has_role(role, account) {
+                self.roles.insert((role, account), &());
+                self.env().emit_event(RoleGranted {
+                    sender,
+                    role,
+                    account,
+                });
+            }
+        }
+
+        fn set_owner(&mut self, new_owner: Option<AccountId>) {
+            let previous_owner = core::mem::replace(&mut self.owner, new_owner);
+            self.env().emit_event(OwnershipTransferred {
+                caller: self.env().caller(),
+                previous_owner,
+                new_owner,
+            });
+        }
+
+        /// Shares worth `value` at the current rebase index, rounded down.
+        fn to_shares(&self, value: Balance) -> Result<Balance> {
+            if self.total_shares == self.total_supply || self.total_supply == 0 {
+                return Ok(value);
+            }
+            Self::mul_div(value, self.total_shares, self.total_supply).ok_or(Error::Overflow)
+        }
+
+        /// Balance `shares` are worth at the current rebase index, rounded
+        /// down.
+        fn to_balance(&self, shares: Balance) -> Balance {
+            Self::shares_to_balance(shares, self.total_supply, self.total_shares)
+        }
+
+        fn shares_to_balance(
+            shares: Balance,
+            total_supply: Balance,
+            total_shares: Balance,
+        ) -> Balance {
+            if total_shares == total_supply || total_shares == 0 {
+                return shares;
+            }
+            // Cannot overflow while `shares` is at most `total_shares`.
+            Self::mul_div(shares, total_supply, total_shares).unwrap_or(Balance::MAX)
+        }
+
+        /// Records the shares of `account` as of the current snapshot,
+        /// unless they already were. Called before every change to them.
+        fn checkpoint_shares(&mut self, account: AccountId) {
+            let count = self.share_checkpoint_count.get(account).unwrap_or_default();
+            let last = count
+                .checked_sub(1)
+                .and_then(|last| self.share_checkpoints.get((account, last)));
+            if self.current_snapshot > last.map_or(0, |(id, _)| id) {
+                self.share_checkpoints.insert(
+                    (account, count),
+                    &(self.current_snapshot, self.shares_of(account)),
+                );
+                self.share_checkpoint_count.insert(account, &(count + 1));
+            }
+        }
+
+        /// Records the total supply and shares as of the current snapshot,
+        /// unless they already were. Called before every change to them.
+        fn checkpoint_supply(&mut self) {
+            let count = self.supply_checkpoint_count;
+            let last = count
+                .checked_sub(1)
+                .and_then(|last| self.supply_checkpoints.get(last));
+            if self.current_snapshot > last.map_or(0, |(id, ..)| id) {
+                self.supply_checkpoints.insert(
+                    count,
+                    &(self.current_snapshot, self.total_supply, self.total_shares),
+                );
+                self.supply_checkpoint_count = count + 1;
+            }
+        }
+
+        /// Position of the first of `count` checkpoints, ordered by
+        /// `key_at`, whose snapshot id or block is at least `key`; `count`
+        /// if there is none.
+        fn find_checkpoint(count: u32, key: u32, key_at: impl Fn(u32) -> u32) -> u32 {
+            let (mut low, mut high) = (0, count);
+            while low < high {
+                let mid = low + (high - low) / 2;
+                if key_at(mid) < key {
+                    low = mid + 1;
+                } else {
+                    high = mid;
+                }
+            }
+            low
+        }
+
+        /// Moves `amount` of voting power from one delegate to another;
+        /// `None` stands for tokens that are minted, burned or undelegated.
+        fn move_votes(&mut self, from: Option<AccountId>, to: Option<AccountId>, amount: Balance) {
+            if from == to || amount == 0 {
+                return;
+            }
+            // Voting power never exceeds `total_shares`, so neither side
+            // can wrap.
+            if let Some(from) = from {
+                self.write_votes(from, |votes| votes.saturating_sub(amount));
+            }
+            if let Some(to) = to {
+                self.write_votes(to, |votes| votes.saturating_add(amount));
+            }
+        }
+
+        fn write_votes(&mut self, delegate: AccountId, update: impl FnOnce(Balance) -> Balance) {
+            let count = self.vote_checkpoint_count.get(delegate).unwrap_or_default();
+            let last = count
+                .checked_sub(1)
+                .and_then(|last| self.vote_checkpoints.get((delegate, last)));
+            let previous_votes = last.map_or(0, |(_, votes)| votes);
+            let new_votes = update(previous_votes);
+            let block = self.env().block_number();
+            // Several changes within a block leave a single checkpoint.
+            if last.is_some_and(|(checkpoint_block, _)| checkpoint_block == block) {
+                self.vote_checkpoints
+                    .insert((delegate, count - 1), &(block, new_votes));
+            } else {
+                self.vote_checkpoints
+                    .insert((delegate, count), &(block, new_votes));
+                self.vote_checkpoint_count.insert(delegate, &(count + 1));
+            }
+            self.env().emit_event(DelegateVotesChanged {
+                caller: self.env().caller(),
+                delegate,
+                previous_votes,
+                new_votes,
+            });
+        }
+
+        fn ensure_snapshot_exists(&self, snapshot_id: SnapshotId) -> Result<()> {
+            if snapshot_id == 0 || snapshot_id > self.current_snapshot {
+                return Err(Error::UnknownSnapshot);
+            }
+            Ok(())
+        }
+
+        fn supply_at(&self, snapshot_id: SnapshotId) -> (Balance, Balance) {
+            let checkpoint_id = |position| {
+                self.supply_checkpoints
+                    .get(position)
+                    .map_or(0, |(id, ..)| id)
+            };
+            let position =
+                Self::find_checkpoint(self.supply_checkpoint_count, snapshot_id, checkpoint_id);
+            self.supply_checkpoints.get(position).map_or(
+                (self.total_supply, self.total_shares),
+                |(_, supply, shares)| (supply, shares),
+            )
+        }
+
+        /// `a * b / c` rounded down, computed on a 256-bit product. `None`
+        /// if `c` is zero or the result does not fit in a `u128`.
+        fn mul_div(a: u128, b: u128, c: u128) -> Option<u128> {
+            if c == 0 {
+                return None;
+            }
+            const LOW: u128 = u64::MAX as u128;
+            let (a_high, a_low) = (a >> 64, a & LOW);
+            let (b_high, b_low) = (b >> 64, b & LOW);
+            let low_low = a_low * b_low;
+            let high_low = a_high * b_low;
+            let low_high = a_low * b_high;
+            let middle = (low_low >> 64) + (high_low & LOW) + (low_high & LOW);
+            let low = (low_low & LOW) | (middle << 64);
+            let high = a_high * b_high + (high_low >> 64) + (low_high >> 64) + (middle >> 64);
+            if high == 0 {
+                return Some(low / c);
+            }
+            if high >= c {
+                return None;
+            }
+            // Long division of `high:low` by `c`, one bit at a time.
+            let (mut quotient, mut remainder) = (0u128, high);
+            for bit in (0..128).rev() {
+                let carry = remainder >> 127;
+                remainder = (remainder << 1) | ((low >> bit) & 1);
+                quotient <<= 1;
+                if carry == 1 || remainder >= c {
+                    remainder = remainder.wrapping_sub(c);
+                    quotient |= 1;
+                }
+            }
+            Some(quotient)
+        }
+
+        fn check_cap(total_supply: Balance, cap: Option<Balance>) -> Result<()> {
+            match cap {
+                Some(cap) if total_supply > cap => Err(Error::CapExceeded),
+                _ => Ok(()),
+            }
+        }
+
+        fn ensure_pauser(&self) -> Result<()> {
+            if !self.has_role(PAUSER, self.env().caller()) {
+                return Err(Error::NotPauser);
+            }
+            Ok(())
+        }
+
+        fn ensure_not_paused(&self) -> Result<()> {
+            if self.paused {
+                return Err(Error::ContractPaused);
+            }
+            Ok(())
+        }
+
+        fn ensure_minter(&self) -> Result<()> {
+            if !self.has_role(MINTER, self.env().caller()) {
+                return Err(Error::NotMinter);
+            }
+            Ok(())
+        }
+
+        fn revoke_role_helper(&mut self, role: RoleId, account: AccountId, sender: AccountId) {
+            if self.has_role(role, account) {
+                self.roles.remove((role, account));
+                self.env().emit_event(RoleRevoked {
+                    sender,
+                    role,
+                    account,
+                });
+            }
+        }
+
+        /// Calls a PSP22 message on `token` that returns
+        /// `Result<(), PSP22Error>`, collapsing every failure into
+        /// `Error::TokenTransferFailed`.
+        fn call_psp22<Args: scale::Encode>(
+            token: AccountId,
+            input: ExecutionInput<Args>,
+        ) -> Result<()> {
+            let result = build_call::<DefaultEnvironment>()
+                .call(token)
+                .gas_limit(0)
+                .exec_input(input)
+                .returns::<core::result::Result<(), PSP22Error>>()
+                .try_invoke();
+            match result {
+                Ok(Ok(Ok(()))) => Ok(()),
+                _ => Err(Error::TokenTransferFailed),
+            }
+        }
+
+        fn spend_allowance(
+            &mut self,
+            owner: &AccountId,
+            spender: &AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            let allowance = self
+                .allowance(*owner, *spender)
+                .checked_sub(value)
+                .ok_or(Error::AllowanceToolow)?;
+            self.allowances.insert((owner, spender), &allowance);
+            Ok(())
+        }
+
+        fn burn_helper(&mut self, from: &AccountId, value: Balance) -> Result<()> {
+            self.ensure_not_paused()?;
+            self.ensure_spendable(from, value)?;
+            let shares = self.to_shares(value)?;
+            let shares_from = self
+                .shares_of(*from)
+                .checked_sub(shares)
+                .ok_or(Error::Underflow)?;
+            let total_supply = self
+                .total_supply
+                .checked_sub(value)
+                .ok_or(Error::Underflow)?;
+            let total_shares = self
+                .total_shares
+                .checked_sub(shares)
+                .ok_or(Error::Underflow)?;
+            self.checkpoint_shares(*from);
+            self.balances.insert(from, &shares_from);
+            self.checkpoint_supply();
+            self.total_supply = total_supply;
+            self.total_shares = total_shares;
+            self.move_votes(self.delegates(*from), None, shares);
+            self.record_burn(from, value);
+
+            self.emit_transfer(Some(*from), None, value);
+
+            Ok(())
+        }
+
+        /// Adds `value` to the total burned from `account`. The total only
+        /// serves reporting, so it saturates rather than fail the burn.
+        fn record_burn(&mut self, account: &AccountId, value: Balance) {
+            let burned = self.burned_by(*account).saturating_add(value);
+            self.burned.insert(account, &burned);
+        }
+
+        /// Hash an owner signs to authorise a permit.
+        fn permit_hash(
+            &self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+            nonce: u64,
+            deadline: Timestamp,
+        ) -> [u8; 32] {
+            self.signed_hash(&(b"permit", owner, spender, value, nonce, deadline))
+        }
+
+        /// Hash a sender signs to have a forwarder relay a transfer.
+        fn meta_tx_hash(
+            &self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            nonce: u64,
+            deadline: Timestamp,
+        ) -> [u8; 32] {
+            self.signed_hash(&(b"transfer", from, to, value, nonce, deadline))
+        }
+
+        /// Hashes a signed payload under the domain separator. Payloads start
+        /// with a tag naming the action, so one kind cannot pass as another.
+        fn signed_hash<T: scale::Encode>(&self, payload: &T) -> [u8; 32] {
+            let mut hash = <Blake2x256 as HashOutput>::Type::default();
+            ink::env::hash_encoded::<Blake2x256, _>(&(self.domain_separator(), payload), &mut hash);
+            hash
+        }
+
+        /// Checks a permit signed by `owner` and consumes its nonce.
+        fn use_permit(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+            deadline: Timestamp,
+            signature: &[u8; 65],
+        ) -> Result<()> {
+            let hash = self.permit_hash(owner, spender, value, self.nonces(owner), deadline);
+            self.use_signature(owner, deadline, &hash, signature)
+        }
+
+        /// Checks that `signer` signed `hash` and that `deadline` has not
+        /// passed, then consumes the signer's nonce.
+        fn use_signature(
+            &mut self,
+            signer: AccountId,
+            deadline: Timestamp,
+            hash: &[u8; 32],
+            signature: &[u8; 65],
+        ) -> Result<()> {
+            if self.env().block_timestamp() > deadline {
+                return Err(Error::SignatureExpired);
+            }
+            let public_key = self
+                .env()
+                .ecdsa_recover(signature, hash)
+                .map_err(|_| Error::InvalidSignature)?;
+            // ECDSA accounts are the BLAKE2 hash of the compressed public key.
+            let mut recovered = <Blake2x256 as HashOutput>::Type::default();
+            ink::env::hash_bytes::<Blake2x256>(&public_key, &mut recovered);
+            if AccountId::from(recovered) != signer {
+                return Err(Error::InvalidSignature);
+            }
+            let next_nonce = self.nonces(signer).checked_add(1).ok_or(Error::Overflow)?;
+            self.nonces.insert(signer, &next_nonce);
+            Ok(())
+        }
+
+        /// Emits `Transfer` and notifies the transfer extension, if one is
+        /// configured.
+        fn emit_transfer(&self, from: Option<AccountId>, to: Option<AccountId>, value: Balance) {
+            self.env().emit_event(Transfer { from, to, value });
+            if let Some(func_id) = self.transfer_extension {
+                ChainExtensionMethod::build(func_id)
+                    .input::<(Option<AccountId>, Option<AccountId>, Balance)>()
+                    .output::<(), false>()
+                    .ignore_error_code()
+                    .call(&(from, to, value));
+            }
+        }
+
+        fn mint_helper(&mut self, to: &AccountId, value: Balance) -> Result<()> {
+            self.ensure_not_paused()?;
+            if self.refuses_incoming.contains(to) {
+                return Err(Error::RecipientRefused);
+            }
+            let total_supply = self
+                .total_supply
+                .checked_add(value)
+                .ok_or(Error::Overflow)?;
+            Self::check_cap(total_supply, self.cap)?;
+            let shares = self.to_shares(value)?;
+            let shares_to = self
+                .shares_of(*to)
+                .checked_add(shares)
+                .ok_or(Error::Overflow)?;
+            let total_shares = self
+                .total_shares
+                .checked_add(shares)
+                .ok_or(Error::Overflow)?;
+            self.checkpoint_shares(*to);
+            self.balances.insert(to, &shares_to);
+            self.checkpoint_supply();
+            self.total_supply = total_supply;
+            self.total_shares = total_shares;
+            self.move_votes(None, self.delegates(*to), shares);
+
+            self.emit_transfer(None, Some(*to), value);
+
+            Ok(())
+        }
+
+        /// Airdrop leaf assigning `amount` to `account` at `index`.
+        fn merkle_leaf(index: u32, account: AccountId, amount: Balance) -> Hash {
+            let mut leaf = <Blake2x256 as HashOutput>::Type::default();
+            ink::env::hash_encoded::<Blake2x256, _>(&(index, account, amount), &mut leaf);
+            leaf.into()
+        }
+
+        /// Parent of two airdrop tree nodes. The pair is sorted first, so a
+        /// proof needs no left/right flags.
+        fn merkle_parent(a: &Hash, b: &Hash) -> Hash {
+            let pair = if a <= b { (a, b) } else { (b, a) };
+            let mut parent = <Blake2x256 as HashOutput>::Type::default();
+            ink::env::hash_encoded::<Blake2x256, _>(&pair, &mut parent);
+            parent.into()
+        }
+
+        fn approve_helper(
+            &mut self,
+            owner: &AccountId,
+            spender: &AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            self.ensure_not_paused()?;
+            // Lowering an allowance is always allowed, so one granted while
+            // the owner had opted out can still be revoked.
+            if value > self.allowance(*owner, *spender) {
+                self.ensure_spender_allowed(owner, spender)?;
+            }
+            self.allowances.insert((owner, spender), &value);
+
+            self.env().emit_event(Approval {
+                from: *owner,
+                to: *spender,
+                value,
+            });
+
+            Ok(())
+        }
+
+        /// Checks that `owner` lets `spender` move their tokens, which
+        /// `contract_spenders_only` limits to contracts.
+        fn ensure_spender_allowed(&self, owner: &AccountId, spender: &AccountId) -> Result<()> {
+            if self.contract_spenders_only(*owner) && !self.env().is_contract(spender) {
+                return Err(Error::SpenderNotContract);
+            }
+            Ok(())
+        }
+
+        /// Checks every restriction a transfer of `value` from `from` to `to`
+        /// is subject to, without changing any state.
+        fn ensure_transferable(
+            &self,
+            from: &AccountId,
+            to: &AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            self.ensure_not_paused()?;
+            if self.refuses_incoming.contains(to) {
+                return Err(Error::RecipientRefused);
+            }
+            self.ensure_spendable(from, value)
+        }
+
+        /// Checks that `from` holds `value` unlocked tokens.
+        fn ensure_spendable(&self, from: &AccountId, value: Balance) -> Result<()> {
+            let balance = self.balance_of(*from);
+            if value > balance {
+                return Err(Error::BalanceTooLow);
+            }
+            if value > balance.saturating_sub(self.locked_balance_of(*from)) {
+                return Err(Error::TokensLocked);
+            }
+            Ok(())
+        }
+
+        /// Emits a `CallTrace` for the current call when built with the
+        /// `telemetry` feature; a no-op otherwise.
+        fn trace_call(&self) {
+            #[cfg(feature = "telemetry")]
+            {
+                let caller = self.env().caller();
+                // Only the origin can call without code of its own, and only
+                // a contract has a code hash to report.
+                let (caller_code_hash, call_depth) = if self.env().is_contract(&caller) {
+                    (self.env().code_hash(&caller).ok(), None)
+                } else {
+                    (None, Some(0))
+                };
+                self.env().emit_event(CallTrace {
+                    caller,
+                    caller_code_hash,
+                    call_depth,
+                });
+            }
+        }
+    }
+
+    /// PSP22 view of the token. Each message forwards to its ERC-20 style
+    /// counterpart, which stays available under its own selector. `data` is
+    /// accepted for compatibility and otherwise ignored.
+    impl PSP22 for Erc20 {
+        #[ink(message)]
+        fn total_supply(&self) -> Balance {
+            Erc20::total_supply(self)
+        }
+
+        #[ink(message)]
+        fn balance_of(&self, owner: AccountId) -> Balance {
+            Erc20::balance_of(self, owner)
+        }
+
+        #[ink(message)]
+        fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            Erc20::allowance(self, owner, spender)
+        }
+
+        #[ink(message)]
+        fn transfer(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> core::result::Result<(), PSP22Error> {
+            Erc20::transfer(self, to, value).map_err(Into::into)
+        }
+
+        #[ink(message)]
+        fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> core::result::Result<(), PSP22Error> {
+            Erc20::transfer_from(self, from, to, value).map_err(Into::into)
+        }
+
+        #[ink(message)]
+        fn approve(
+            &mut self,
+            spender: AccountId,
+            value: Balance,
+        ) -> core::result::Result<(), PSP22Error> {
+            Erc20::approve(self, spender, value).map_err(Into::into)
+        }
+
+        #[ink(message)]
+        fn increase_allowance(
+            &mut self,
+            spender: AccountId,
+            delta_value: Balance,
+        ) -> core::result::Result<(), PSP22Error> {
+            Erc20::increase_allowance(self, spender, delta_value).map_err(Into::into)
+        }
+
+        #[ink(message)]
+        fn decrease_allowance(
+            &mut self,
+            spender: AccountId,
+            delta_value: Balance,
+        ) -> core::result::Result<(), PSP22Error> {
+            Erc20::decrease_allowance(self, spender, delta_value).map_err(Into::into)
+        }
+    }
+
+    impl PSP22Metadata for Erc20 {
+        #[ink(message)]
+        fn token_name(&self) -> Option<String> {
+            Erc20::token_name(self)
+        }
+
+        #[ink(message)]
+        fn token_symbol(&self) -> Option<String> {
+            Erc20::token_symbol(self)
+        }
+
+        #[ink(message)]
+        fn token_decimals(&self) -> u8 {
+            Erc20::token_decimals(self)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::testing::{
+            accounts, assert_approval_event, assert_transfer_event, contract_account, decode_event,
+            recorded_events, set_caller, Event, Scenario,
+        };
+
+        #[test]
+        fn message_selectors_are_stable() {
+            use ink::reflect::DispatchableMessageInfo;
+
+            macro_rules! assert_selector {
+                ($selector:literal, $label:literal) => {
+                    assert_eq!(<Erc20 as DispatchableMessageInfo<$selector>>::LABEL, $label);
+                    assert_eq!(
+                        <Erc20 as DispatchableMessageInfo<$selector>>::SELECTOR,
+                        u32::to_be_bytes($selector)
+                    );
+                };
+            }
+
+            assert_selector!(0xDB6375A8, "total_supply");
+            assert_selector!(0x0F755A56, "balance_of");
+            assert_selector!(0xC4D9FB2D, "deployment_id");
+            assert_selector!(0xFFE9F9CF, "chain_id");
+            assert_selector!(0x84A15DA1, "transfer");
+            assert_selector!(0x0B396F18, "transfer_from");
+            assert_selector!(0x681266A0, "approve");
+            assert_selector!(0x12561DF8, "set_contract_spenders_only");
+            assert_selector!(0x4A8BDF58, "contract_spenders_only");
+            assert_selector!(0x1A3F413F, "set_refuse_incoming");
+            assert_selector!(0x2DD0EC27, "refuses_incoming");
+            assert_selector!(0x77BCAAB3, "register_tag");
+            assert_selector!(0x0D48B4AA, "resolve_tag");
+            assert_selector!(0xE8C677A3, "transfer_to_tag");
+            assert_selector!(0x01E3A5EB, "simulate_transfer");
+            assert_selector!(0x6A00165E, "allowance");
+            assert_selector!(0xA3B7D8EB, "locked_balance_of");
+            assert_selector!(0x14F5EA79, "genesis_schedule");
+            assert_selector!(0xF551D422, "increase_allowance");
+            assert_selector!(0xF998EBD9, "decrease_allowance");
+            assert_selector!(0x53DB29AE, "token_name");
+            assert_selector!(0xD733EA17, "token_symbol");
+            assert_selector!(0x9715E3CA, "token_decimals");
+            assert_selector!(0xCFDD9AA2, "mint");
+            assert_selector!(0xE55FB130, "add_minter");
+            assert_selector!(0xE4873304, "remove_minter");
+            assert_selector!(0x13FC1F2D, "is_minter");
+            assert_selector!(0x567C1EAE, "lock_tokens");
+            assert_selector!(0x4BC9BB48, "withdraw_lock");
+            assert_selector!(0x5FEB3C92, "token_lock");
+            assert_selector!(0x68254F77, "total_locked");
+            assert_selector!(0xB1EFC17B, "burn");
+            assert_selector!(0x27212BBB, "burn_from");
+            assert_selector!(0xFEAEA4FA, "owner");
+            assert_selector!(0x6E429D8D, "pending_owner");
+            assert_selector!(0x107E33EA, "transfer_ownership");
+            assert_selector!(0xB55BE9F0, "accept_ownership");
+            assert_selector!(0x8C90065B, "renounce_ownership");
+            assert_selector!(0x2AABFAB5, "grant_role");
+            assert_selector!(0x35E1EF4A, "revoke_role");
+            assert_selector!(0xFAB8657B, "renounce_role");
+            assert_selector!(0x8D194A68, "has_role");
+            assert_selector!(0x81E0C604, "pause");
+            assert_selector!(0x67616649, "unpause");
+            assert_selector!(0xD8FEA916, "paused");
+            assert_selector!(0xB00B03C6, "cap");
+            assert_selector!(0x162DF8C2, "PSP22::total_supply");
+            assert_selector!(0x6568382F, "PSP22::balance_of");
+            assert_selector!(0x4D47D921, "PSP22::allowance");
+            assert_selector!(0xDB20F9F5, "PSP22::transfer");
+            assert_selector!(0x54B3C76E, "PSP22::transfer_from");
+            assert_selector!(0xB20F1BBD, "PSP22::approve");
+            assert_selector!(0x96D6B57A, "PSP22::increase_allowance");
+            assert_selector!(0xFECB57D5, "PSP22::decrease_allowance");
+            assert_selector!(0x3D261BD4, "PSP22Metadata::token_name");
+            assert_selector!(0x34205BE5, "PSP22Metadata::token_symbol");
+            assert_selector!(0x7271B782, "PSP22Metadata::token_decimals");
+            assert_selector!(0xACD10E50, "transfer_and_call");
+            assert_selector!(0x84AFF499, "permit");
+            assert_selector!(0x7D7E596C, "nonces");
+            assert_selector!(0xB7F73B4A, "domain_separator");
+            assert_selector!(0xC53189E3, "transfer_with_permit");
+            assert_selector!(0xD41D7E38, "set_trusted_forwarder");
+            assert_selector!(0x91C55059, "trusted_forwarder");
+            assert_selector!(0x58158CF9, "execute_meta_tx");
+            assert_selector!(0x810861B9, "transfer_batch");
+            assert_selector!(0x1DE1A03B, "transfer_with_envelope");
+            assert_selector!(0x97D5F295, "balances_of");
+            assert_selector!(0x3ED08818, "allowances_of");
+            assert_selector!(0x6A51792C, "set_transfer_extension");
+            assert_selector!(0x63B62CB9, "transfer_extension");
+            assert_selector!(0xC4671281, "set_merkle_root");
+            assert_selector!(0x7EDF9E0E, "merkle_root");
+            assert_selector!(0xB388803F, "claim");
+            assert_selector!(0xC58EED46, "is_claimed");
+            assert_selector!(0xB471202D, "create_vesting");
+            assert_selector!(0x3F2BE152, "release");
+            assert_selector!(0x978A3F5A, "vested_amount");
+            assert_selector!(0xE0875381, "releasable");
+            assert_selector!(0xB27C6226, "vesting_grant");
+            assert_selector!(0x9DB83196, "schedule");
+            assert_selector!(0xD00EC894, "execute");
+            assert_selector!(0x9796E9A7, "cancel");
+            assert_selector!(0x244B6C08, "operation_ready_at");
+            assert_selector!(0xB99947D1, "min_delay");
+            assert_selector!(0x5517ECE1, "set_min_delay");
+            assert_selector!(0xD3F73E00, "set_fee_bps");
+            assert_selector!(0xC5B70D50, "set_fee_collector");
+            assert_selector!(0xC374761C, "transfer_fee");
+            assert_selector!(0x04C1D059, "fee_collector");
+            assert_selector!(0x91F18B9D, "add_fee_exempt");
+            assert_selector!(0x4437F826, "remove_fee_exempt");
+            assert_selector!(0x77D423C1, "is_fee_exempt");
+            assert_selector!(0xA4FF7BEF, "set_burn_bps");
+            assert_selector!(0x1FB21571, "burn_bps");
+            assert_selector!(0x3952B95B, "fees_paid");
+            assert_selector!(0x355A81E1, "burned_by");
+            assert_selector!(0x9ACE6980, "rebase");
+            assert_selector!(0xC0086DF1, "shares_of");
+            assert_selector!(0xACA19D26, "total_shares");
+            assert_selector!(0x798ADA01, "snapshot");
+            assert_selector!(0x7D4B55A1, "current_snapshot");
+            assert_selector!(0x5A2F8344, "balance_of_at");
+            assert_selector!(0x3727369D, "total_supply_at");
+            assert_selector!(0xC59654FE, "delegate");
+            assert_selector!(0x804C53D9, "delegates");
+            assert_selector!(0x5F9D374C, "get_votes");
+            assert_selector!(0x7995D5FC, "get_past_votes");
+        }
+
+        #[test]
+        fn nothing_accepts_native_funds() {
+            use ink::reflect::{DispatchableConstructorInfo, DispatchableMessageInfo};
+
+            const { assert!(!<Erc20 as DispatchableConstructorInfo<0x9BAE9D5E>>::PAYABLE) };
+            const { assert!(!<Erc20 as DispatchableConstructorInfo<0x10D1B49A>>::PAYABLE) };
+            const { assert!(!<Erc20 as DispatchableConstructorInfo<0xC72C881D>>::PAYABLE) };
+
+            macro_rules! assert_not_payable {
+                ($($selector:literal),* $(,)?) => {
+                    $(const { assert!(!<Erc20 as DispatchableMessageInfo<$selector>>::PAYABLE) };)*
+                };
+            }
+
+            assert_not_payable!(
+                0xDB6375A8, 0x0F755A56, 0xC4D9FB2D, 0x84A15DA1, 0x0B396F18, 0x681266A0, 0x12561DF8,
+                0x4A8BDF58, 0x1A3F413F, 0x2DD0EC27, 0x77BCAAB3, 0x0D48B4AA, 0xE8C677A3, 0x01E3A5EB,
+                0x6A00165E, 0xA3B7D8EB, 0x14F5EA79, 0xF551D422, 0xF998EBD9, 0x53DB29AE, 0xD733EA17,
+                0x9715E3CA, 0xCFDD9AA2, 0xE55FB130, 0xE4873304, 0x13FC1F2D, 0x567C1EAE, 0x4BC9BB48,
+                0x5FEB3C92, 0x68254F77, 0xB1EFC17B, 0x27212BBB, 0xFEAEA4FA, 0x6E429D8D, 0x107E33EA,
+                0xB55BE9F0, 0x8C90065B, 0x2AABFAB5, 0x35E1EF4A, 0xFAB8657B, 0x8D194A68, 0x81E0C604,
+                0x67616649, 0xD8FEA916, 0xB00B03C6, 0x162DF8C2, 0x6568382F, 0x4D47D921, 0xDB20F9F5,
+                0x54B3C76E, 0xB20F1BBD, 0x96D6B57A, 0xFECB57D5, 0x3D261BD4, 0x34205BE5, 0x7271B782,
+                0xACD10E50, 0x84AFF499, 0x7D7E596C, 0xB7F73B4A, 0xC53189E3, 0xD41D7E38, 0x91C55059,
+                0x58158CF9, 0x810861B9, 0x1DE1A03B, 0x97D5F295, 0x3ED08818, 0x6A51792C, 0x63B62CB9,
+                0xC4671281, 0x7EDF9E0E, 0xB388803F, 0xC58EED46, 0xB471202D, 0x3F2BE152, 0x978A3F5A,
+                0xE0875381, 0xB27C6226, 0x9DB83196, 0xD00EC894, 0x9796E9A7, 0x244B6C08, 0xB99947D1,
+                0x5517ECE1, 0xD3F73E00, 0xC5B70D50, 0xC374761C, 0x04C1D059, 0x91F18B9D, 0x4437F826,
+                0x77D423C1, 0xA4FF7BEF, 0x1FB21571, 0x9ACE6980, 0xC0086DF1, 0xACA19D26, 0x798ADA01,
+                0x7D4B55A1, 0x5A2F8344, 0x3727369D, 0xC59654FE, 0x804C53D9, 0x5F9D374C, 0x7995D5FC,
+                0xFFE9F9CF, 0x3952B95B, 0x355A81E1,
+            );
+        }
+
+        #[ink::test]
+        fn constructor_works() {
+            let erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            assert_eq!(erc20.total_supply(), 1000);
+
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.balance_of(accounts.alice), 1000);
+
+            let emitted_events = recorded_events();
+            assert_eq!(emitted_events.len(), 3);
+            assert_transfer_event(&emitted_events[0], None, Some(accounts.alice), 1000);
+            match decode_event(&emitted_events[1]) {
+                Event::OwnershipTransferred(OwnershipTransferred {
+                    caller,
+                    previous_owner,
+                    new_owner,
+                }) => {
+                    assert_eq!(caller, accounts.alice);
+                    assert_eq!(previous_owner, None);
+                    assert_eq!(new_owner, Some(accounts.alice));
+                }
+                _ => panic!("expected an OwnershipTransferred event"),
+            }
+        }
+
+        #[ink::test]
+        fn metadata_is_stored() {
+            let erc20 = Erc20::new(
+                1000,
+                Some(String::from("Example")),
+                Some(String::from("EXM")),
+                12,
+                None,
+                Hash::default(),
+            );
+            assert_eq!(erc20.token_name(), Some(String::from("Example")));
+            assert_eq!(erc20.token_symbol(), Some(String::from("EXM")));
+            assert_eq!(erc20.token_decimals(), 12);
+            assert_eq!(
+                PSP22Metadata::token_name(&erc20),
+                Some(String::from("Example"))
+            );
+            assert_eq!(
+                PSP22Metadata::token_symbol(&erc20),
+                Some(String::from("EXM"))
+            );
+            assert_eq!(PSP22Metadata::token_decimals(&erc20), 12);
+
+            let erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            assert_eq!(erc20.token_name(), None);
+            assert_eq!(erc20.token_symbol(), None);
+        }
+
+        #[ink::test]
+        fn new_human_scales_by_decimals() {
+            let erc20 =
+                Erc20::new_human(1000, None, None, 18, None, Hash::default()).expect("supply fits");
+            assert_eq!(erc20.token_decimals(), 18);
+            assert_eq!(erc20.total_supply(), 1000 * 10u128.pow(18));
+            assert_eq!(erc20.balance_of(accounts().alice), 1000 * 10u128.pow(18));
+        }
+
+        #[ink::test]
+        fn new_human_rejects_overflowing_supply() {
+            assert!(matches!(
+                Erc20::new_human(u128::MAX, None, None, 1, None, Hash::default()),
+                Err(Error::Overflow)
+            ));
+            assert!(matches!(
+                Erc20::new_human(1, None, None, 39, None, Hash::default()),
+                Err(Error::Overflow)
+            ));
+        }
+
+        #[ink::test]
+        fn deployment_id_depends_on_instantiation_block() {
+            let first = Erc20::new(1000, None, None, 0, None, Hash::default());
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            let second = Erc20::new(1000, None, None, 0, None, Hash::default());
+            assert_ne!(first.deployment_id(), Hash::default());
+            assert_ne!(first.deployment_id(), second.deployment_id());
+        }
+
+        #[ink::test]
+        fn transfer_should_work() {
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let res = erc20.transfer(accounts.bob, 12);
+            assert_eq!(res, Ok(()));
+            assert_eq!(erc20.balance_of(accounts.alice), 1000 - 12);
+            assert_eq!(erc20.balance_of(accounts.bob), 12);
+
+            let emitted_events = recorded_events();
+            assert_transfer_event(
+                &emitted_events[3],
+                Some(accounts.alice),
+                Some(accounts.bob),
+                12,
+            );
+        }
+
+        #[ink::test]
+        fn invalid_transfer_should_fail() {
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
             let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            let res = erc20.transfer(accounts.charlie, 12);
+            assert_eq!(res, Err(Error::BalanceTooLow));
+            /* let res = erc20.transfer(accounts.bob, 1001);
+            assert_eq!(res, Err(Error::BalanceTooLow));
+            assert_eq!(erc20.balance_of(accounts.alice), 1000);
+            assert_eq!(erc20.balance_of(accounts.bob), 0); */
+        }
+
+        #[ink::test]
+        fn transfer_from_should_work() {
+            let accounts = accounts();
+            let mut erc20 = Scenario::new()
+                .with_balance(accounts.alice, 1000)
+                .with_allowance(accounts.alice, accounts.bob, 100)
+                .build();
+
+            let emitted_events = recorded_events();
+            assert_approval_event(&emitted_events[3], accounts.alice, accounts.bob, 100);
+
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.charlie, 60),
+                Ok(())
+            );
+            assert_eq!(erc20.balance_of(accounts.charlie), 60);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 40);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.charlie, 60),
+                Err(Error::AllowanceToolow)
+            );
+        }
+
+        #[ink::test]
+        fn contract_spenders_only_rejects_plain_accounts() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            assert!(erc20.contract_spenders_only(accounts.alice));
+            assert_eq!(
+                erc20.approve(accounts.bob, 10),
+                Err(Error::SpenderNotContract)
+            );
+
+            ink::env::test::set_contract::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(erc20.approve(accounts.django, 10), Ok(()));
+
+            erc20.set_contract_spenders_only(false);
+            assert!(!erc20.contract_spenders_only(accounts.alice));
+            assert_eq!(erc20.approve(accounts.bob, 10), Ok(()));
+
+            erc20.set_contract_spenders_only(true);
+            assert_eq!(
+                erc20.approve(accounts.bob, 11),
+                Err(Error::SpenderNotContract)
+            );
+            assert_eq!(
+                erc20.increase_allowance(accounts.bob, 1),
+                Err(Error::SpenderNotContract)
+            );
+
+            // The allowance granted while opted out can still be revoked.
+            assert_eq!(erc20.decrease_allowance(accounts.bob, 4), Ok(()));
+            assert_eq!(erc20.approve(accounts.bob, 0), Ok(()));
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn refused_recipient_cannot_receive() {
+            let accounts = accounts();
+            let mut erc20 = Scenario::new()
+                .with_balance(accounts.alice, 1000)
+                .with_allowance(accounts.alice, accounts.charlie, 100)
+                .build();
+
+            set_caller(accounts.bob);
+            erc20.set_refuse_incoming(true);
+            assert!(erc20.refuses_incoming(accounts.bob));
+
+            set_caller(accounts.alice);
+            assert_eq!(
+                erc20.transfer(accounts.bob, 10),
+                Err(Error::RecipientRefused)
+            );
+            set_caller(accounts.charlie);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.bob, 10),
+                Err(Error::RecipientRefused)
+            );
+            set_caller(accounts.alice);
+            assert_eq!(erc20.mint(accounts.bob, 10), Err(Error::RecipientRefused));
+
+            set_caller(accounts.bob);
+            erc20.set_refuse_incoming(false);
+            set_caller(accounts.alice);
+            assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+        }
+
+        #[ink::test]
+        fn transfer_to_tag_works() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+
+            set_caller(accounts.bob);
+            assert_eq!(erc20.register_tag(String::from("bob-pay")), Ok(()));
+            assert_eq!(
+                erc20.resolve_tag(String::from("bob-pay")),
+                Some(accounts.bob)
+            );
+
+            set_caller(accounts.alice);
+            assert_eq!(
+                erc20.register_tag(String::from("bob-pay")),
+                Err(Error::TagTaken)
+            );
+            assert_eq!(erc20.transfer_to_tag(String::from("bob-pay"), 25), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 25);
+            assert_eq!(
+                erc20.transfer_to_tag(String::from("nobody"), 25),
+                Err(Error::UnknownTag)
+            );
+        }
+
+        #[ink::test]
+        fn register_tag_rejects_invalid_tags() {
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            for tag in ["", "Bob", "bob pay", "bób", &"a".repeat(MAX_TAG_LEN + 1)] {
+                assert_eq!(
+                    erc20.register_tag(String::from(tag)),
+                    Err(Error::InvalidTag)
+                );
+            }
+            assert_eq!(erc20.register_tag("a".repeat(MAX_TAG_LEN)), Ok(()));
+        }
+
+        #[ink::test]
+        fn simulate_transfer_reports_restrictions() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+
+            let simulation = erc20.simulate_transfer(accounts.alice, accounts.bob, 100);
+            assert_eq!(
+                simulation,
+                TransferSimulation {
+                    net: 100,
+                    fee: 0,
+                    burned: 0,
+                    blocked_by: None,
+                }
+            );
+            assert_eq!(
+                erc20
+                    .simulate_transfer(accounts.alice, accounts.bob, 1001)
+                    .blocked_by,
+                Some(Error::BalanceTooLow)
+            );
+
+            set_caller(accounts.bob);
+            erc20.set_refuse_incoming(true);
+            assert_eq!(
+                erc20
+                    .simulate_transfer(accounts.alice, accounts.bob, 100)
+                    .blocked_by,
+                Some(Error::RecipientRefused)
+            );
+        }
+
+        #[ink::test]
+        fn allowance_reflects_approvals() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+
+            erc20.set_contract_spenders_only(false);
+            assert_eq!(erc20.approve(accounts.bob, 50), Ok(()));
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 50);
+            assert_eq!(erc20.allowance(accounts.bob, accounts.alice), 0);
+
+            assert_eq!(erc20.approve(accounts.bob, 0), Ok(()));
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn genesis_allocations_unlock_per_epoch() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new_with_genesis_locks(
+                1000,
+                None,
+                None,
+                0,
+                None,
+                Hash::default(),
+                vec![(accounts.bob, 100)],
+                2,
+                4,
+            )
+            .expect("valid schedule");
+            assert_eq!(erc20.balance_of(accounts.bob), 100);
+            assert_eq!(erc20.locked_balance_of(accounts.bob), 100);
+
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.transfer(accounts.charlie, 1),
+                Err(Error::TokensLocked)
+            );
+
+            for _ in 0..2 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(erc20.locked_balance_of(accounts.bob), 75);
+            assert_eq!(erc20.transfer(accounts.charlie, 25), Ok(()));
+            assert_eq!(
+                erc20.transfer(accounts.charlie, 1),
+                Err(Error::TokensLocked)
+            );
+
+            for _ in 0..6 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            assert_eq!(erc20.locked_balance_of(accounts.bob), 0);
+            assert_eq!(erc20.transfer(accounts.charlie, 75), Ok(()));
+        }
+
+        #[ink::test]
+        fn genesis_locks_follow_rebases() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new_with_genesis_locks(
+                1000,
+                None,
+                None,
+                0,
+                None,
+                Hash::default(),
+                vec![(accounts.bob, 100)],
+                2,
+                4,
+            )
+            .expect("valid schedule");
+            assert_eq!(erc20.rebase(1000), Ok(()));
+            assert_eq!(erc20.locked_balance_of(accounts.bob), 200);
+
+            for _ in 0..2 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            set_caller(accounts.bob);
+            assert_eq!(erc20.locked_balance_of(accounts.bob), 150);
+            assert_eq!(erc20.transfer(accounts.charlie, 50), Ok(()));
+            assert_eq!(
+                erc20.transfer(accounts.charlie, 1),
+                Err(Error::TokensLocked)
+            );
+        }
+
+        #[ink::test]
+        fn genesis_locks_reject_empty_schedule() {
+            let accounts = accounts();
+            assert!(matches!(
+                Erc20::new_with_genesis_locks(
+                    1000,
+                    None,
+                    None,
+                    0,
+                    None,
+                    Hash::default(),
+                    vec![(accounts.bob, 100)],
+                    0,
+                    4
+                ),
+                Err(Error::InvalidSchedule)
+            ));
+            assert!(matches!(
+                Erc20::new_with_genesis_locks(
+                    1000,
+                    None,
+                    None,
+                    0,
+                    None,
+                    Hash::default(),
+                    vec![(accounts.bob, 100)],
+                    2,
+                    0
+                ),
+                Err(Error::InvalidSchedule)
+            ));
+            assert!(matches!(
+                Erc20::new_with_genesis_locks(
+                    1000,
+                    None,
+                    None,
+                    0,
+                    None,
+                    Hash::default(),
+                    vec![(accounts.bob, 1001)],
+                    2,
+                    4
+                ),
+                Err(Error::BalanceTooLow)
+            ));
+        }
+
+        #[ink::test]
+        fn increase_and_decrease_allowance_work() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            erc20.set_contract_spenders_only(false);
+
+            assert_eq!(erc20.increase_allowance(accounts.bob, 30), Ok(()));
+            assert_eq!(erc20.increase_allowance(accounts.bob, 20), Ok(()));
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 50);
+            assert_eq!(erc20.decrease_allowance(accounts.bob, 15), Ok(()));
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 35);
+
+            assert_eq!(
+                erc20.decrease_allowance(accounts.bob, 36),
+                Err(Error::AllowanceToolow)
+            );
+            assert_eq!(
+                erc20.increase_allowance(accounts.bob, u128::MAX),
+                Err(Error::Overflow)
+            );
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 35);
+
+            let emitted_events = recorded_events();
+            assert_approval_event(&emitted_events[5], accounts.alice, accounts.bob, 35);
+        }
+
+        #[ink::test]
+        fn mint_works_for_minters_only() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            assert!(erc20.is_minter(accounts.alice));
+
+            assert_eq!(erc20.mint(accounts.bob, 500), Ok(()));
+            assert_eq!(erc20.total_supply(), 1500);
+            assert_eq!(erc20.balance_of(accounts.bob), 500);
+            assert_transfer_event(&recorded_events()[3], None, Some(accounts.bob), 500);
+
+            set_caller(accounts.bob);
+            assert_eq!(erc20.mint(accounts.bob, 1), Err(Error::NotMinter));
+            assert_eq!(erc20.add_minter(accounts.bob), Err(Error::NotOwner));
+
+            set_caller(accounts.alice);
+            assert_eq!(erc20.add_minter(accounts.bob), Ok(()));
+            set_caller(accounts.bob);
+            assert_eq!(erc20.mint(accounts.bob, 1), Ok(()));
+            assert_eq!(erc20.remove_minter(accounts.bob), Err(Error::NotOwner));
+
+            set_caller(accounts.alice);
+            assert_eq!(erc20.remove_minter(accounts.bob), Ok(()));
+            assert!(!erc20.is_minter(accounts.bob));
+            set_caller(accounts.bob);
+            assert_eq!(erc20.mint(accounts.bob, 1), Err(Error::NotMinter));
+        }
+
+        #[ink::test]
+        fn mint_rejects_supply_overflow() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            assert_eq!(erc20.mint(accounts.bob, u128::MAX), Err(Error::Overflow));
+            assert_eq!(erc20.total_supply(), 1000);
+        }
+
+        #[ink::test]
+        fn locked_tokens_withdraw_after_unlock() {
+            let accounts = accounts();
+            let locker = accounts.frank;
+            let mut erc20 = Scenario::new()
+                .with_balance(accounts.alice, 1000)
+                .deployed_at(locker)
+                .build();
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(100);
+
+            assert_eq!(
+                erc20.lock_tokens(locker, 300, 100),
+                Err(Error::InvalidUnlockTime)
+            );
+            assert_eq!(erc20.lock_tokens(locker, 300, 200), Ok(0));
+            assert_eq!(erc20.balance_of(accounts.alice), 700);
+            match decode_event(recorded_events().last().expect("event recorded")) {
+                Event::TokensLocked(TokensLocked {
+                    owner,
+                    token,
+                    id,
+                    amount,
+                    unlock_at,
+                }) => {
+                    assert_eq!((owner, token), (accounts.alice, locker));
+                    assert_eq!((id, amount, unlock_at), (0, 300, 200));
+                }
+                _ => panic!("expected a TokensLocked event"),
+            }
+            assert_eq!(erc20.total_locked(locker), 300);
+            assert_eq!(
+                erc20.token_lock(0),
+                Some(TokenLock {
+                    token: locker,
+                    owner: accounts.alice,
+                    amount: 300,
+                    unlock_at: 200,
+                })
+            );
+
+            assert_eq!(erc20.withdraw_lock(0), Err(Error::StillLocked));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(200);
+            set_caller(accounts.bob);
+            assert_eq!(erc20.withdraw_lock(0), Err(Error::NotLockOwner));
+
+            set_caller(accounts.alice);
+            assert_eq!(erc20.withdraw_lock(0), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.alice), 1000);
+            match decode_event(recorded_events().last().expect("event recorded")) {
+                Event::LockWithdrawn(LockWithdrawn {
+                    owner,
+                    token,
+                    id,
+                    amount,
+                }) => {
+                    assert_eq!((owner, token), (accounts.alice, locker));
+                    assert_eq!((id, amount), (0, 300));
+                }
+                _ => panic!("expected a LockWithdrawn event"),
+            }
+            assert_eq!(erc20.total_locked(locker), 0);
+            assert_eq!(erc20.withdraw_lock(0), Err(Error::UnknownLock));
+        }
+
+        #[ink::test]
+        fn burn_works() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+
+            assert_eq!(erc20.burn(100), Ok(()));
+            assert_eq!(erc20.total_supply(), 900);
+            assert_eq!(erc20.balance_of(accounts.alice), 900);
+            assert_transfer_event(&recorded_events()[3], Some(accounts.alice), None, 100);
+
+            assert_eq!(erc20.burn(901), Err(Error::BalanceTooLow));
+            assert_eq!(erc20.total_supply(), 900);
+        }
+
+        #[ink::test]
+        fn burn_from_spends_allowance() {
+            let accounts = accounts();
+            let mut erc20 = Scenario::new()
+                .with_balance(accounts.alice, 1000)
+                .with_allowance(accounts.alice, accounts.bob, 100)
+                .build();
+
+            set_caller(accounts.bob);
+            assert_eq!(erc20.burn_from(accounts.alice, 60), Ok(()));
+            assert_eq!(erc20.total_supply(), 940);
+            assert_eq!(erc20.balance_of(accounts.alice), 940);
+            assert_eq!(erc20.allowance(accounts.alice, accounts.bob), 40);
+            assert_eq!(
+                erc20.burn_from(accounts.alice, 41),
+                Err(Error::AllowanceToolow)
+            );
+        }
+
+        #[ink::test]
+        fn ownership_transfer_takes_two_steps() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            assert_eq!(erc20.owner(), Some(accounts.alice));
+
+            set_caller(accounts.bob);
+            assert_eq!(erc20.transfer_ownership(accounts.bob), Err(Error::NotOwner));
+
+            set_caller(accounts.alice);
+            assert_eq!(erc20.transfer_ownership(accounts.bob), Ok(()));
+            assert_eq!(erc20.owner(), Some(accounts.alice));
+            assert_eq!(erc20.pending_owner(), Some(accounts.bob));
+
+            set_caller(accounts.charlie);
+            assert_eq!(erc20.accept_ownership(), Err(Error::NotPendingOwner));
+
+            set_caller(accounts.bob);
+            assert_eq!(erc20.accept_ownership(), Ok(()));
+            assert_eq!(erc20.owner(), Some(accounts.bob));
+            assert_eq!(erc20.pending_owner(), None);
+            match decode_event(recorded_events().last().expect("event recorded")) {
+                Event::OwnershipTransferred(OwnershipTransferred {
+                    caller,
+                    previous_owner,
+                    new_owner,
+                }) => {
+                    assert_eq!(caller, accounts.bob);
+                    assert_eq!(previous_owner, Some(accounts.alice));
+                    assert_eq!(new_owner, Some(accounts.bob));
+                }
+                _ => panic!("expected an OwnershipTransferred event"),
+            }
+        }
+
+        #[ink::test]
+        fn renounced_ownership_disables_owner_messages() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            assert_eq!(erc20.transfer_ownership(accounts.bob), Ok(()));
+            assert_eq!(erc20.renounce_ownership(), Ok(()));
+            assert_eq!(erc20.owner(), None);
+            assert_eq!(erc20.pending_owner(), None);
+            assert_eq!(erc20.add_minter(accounts.bob), Err(Error::NotOwner));
+
+            set_caller(accounts.bob);
+            assert_eq!(erc20.accept_ownership(), Err(Error::NotPendingOwner));
+        }
+
+        #[ink::test]
+        fn roles_are_granted_revoked_and_renounced() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            let role = ink::selector_id!("AUDITOR");
+            assert!(erc20.has_role(MINTER, accounts.alice));
+            assert!(!erc20.has_role(role, accounts.bob));
+
+            set_caller(accounts.bob);
+            assert_eq!(erc20.grant_role(role, accounts.bob), Err(Error::NotOwner));
+
+            set_caller(accounts.alice);
+            assert_eq!(erc20.grant_role(role, accounts.bob), Ok(()));
+            assert!(erc20.has_role(role, accounts.bob));
+            assert!(!erc20.has_role(MINTER, accounts.bob));
+            match decode_event(recorded_events().last().expect("event recorded")) {
+                Event::RoleGranted(RoleGranted {
+                    role: granted,
+                    account,
+                    sender,
+                }) => {
+                    assert_eq!(granted, role);
+                    assert_eq!(account, accounts.bob);
+                    assert_eq!(sender, accounts.alice);
+                }
+                _ => panic!("expected a RoleGranted event"),
+            }
+
+            let events_before = recorded_events().len();
+            assert_eq!(erc20.grant_role(role, accounts.bob), Ok(()));
+            assert_eq!(recorded_events().len(), events_before);
+
+            set_caller(accounts.bob);
+            assert_eq!(erc20.revoke_role(role, accounts.bob), Err(Error::NotOwner));
+            assert_eq!(erc20.renounce_role(role), Ok(()));
+            assert!(!erc20.has_role(role, accounts.bob));
+
+            set_caller(accounts.alice);
+            assert_eq!(erc20.revoke_role(MINTER, accounts.alice), Ok(()));
+            assert!(!erc20.has_role(MINTER, accounts.alice));
+            match decode_event(recorded_events().last().expect("event recorded")) {
+                Event::RoleRevoked(RoleRevoked { role, account, .. }) => {
+                    assert_eq!(role, MINTER);
+                    assert_eq!(account, accounts.alice);
+                }
+                _ => panic!("expected a RoleRevoked event"),
+            }
+        }
+
+        #[ink::test]
+        fn pause_halts_token_operations() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            assert_eq!(erc20.pause(), Err(Error::NotPauser));
+            assert_eq!(erc20.grant_role(PAUSER, accounts.alice), Ok(()));
+            erc20.set_contract_spenders_only(false);
+            assert_eq!(erc20.approve(accounts.bob, 100), Ok(()));
+
+            assert_eq!(erc20.pause(), Ok(()));
+            assert!(erc20.paused());
+            assert_eq!(erc20.transfer(accounts.bob, 1), Err(Error::ContractPaused));
+            assert_eq!(erc20.approve(accounts.bob, 1), Err(Error::ContractPaused));
+            assert_eq!(erc20.mint(accounts.bob, 1), Err(Error::ContractPaused));
+            assert_eq!(erc20.burn(1), Err(Error::ContractPaused));
+            assert_eq!(
+                erc20
+                    .simulate_transfer(accounts.alice, accounts.bob, 1)
+                    .blocked_by,
+                Some(Error::ContractPaused)
+            );
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.transfer_from(accounts.alice, accounts.bob, 1),
+                Err(Error::ContractPaused)
+            );
+            assert_eq!(erc20.unpause(), Err(Error::NotPauser));
+
+            set_caller(accounts.alice);
+            assert_eq!(erc20.unpause(), Ok(()));
+            assert!(!erc20.paused());
+            assert_eq!(erc20.transfer(accounts.bob, 1), Ok(()));
+        }
+
+        #[ink::test]
+        fn mint_respects_cap() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, Some(1500), Hash::default());
+            assert_eq!(erc20.cap(), Some(1500));
+
+            assert_eq!(erc20.mint(accounts.bob, 500), Ok(()));
+            assert_eq!(erc20.mint(accounts.bob, 1), Err(Error::CapExceeded));
+            assert_eq!(erc20.total_supply(), 1500);
+
+            assert_eq!(
+                Erc20::new(1000, None, None, 0, None, Hash::default()).cap(),
+                None
+            );
+            assert!(matches!(
+                Erc20::new_human(2, None, None, 3, Some(1999), Hash::default()),
+                Err(Error::CapExceeded)
+            ));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "initial supply exceeds cap")]
+        fn new_rejects_supply_above_cap() {
+            Erc20::new(1000, None, None, 0, Some(999), Hash::default());
+        }
+
+        #[ink::test]
+        fn self_transfer_keeps_balance() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            assert_eq!(erc20.transfer(accounts.alice, 400), Ok(()));
             assert_eq!(erc20.balance_of(accounts.alice), 1000);
+            assert_eq!(erc20.total_supply(), 1000);
+        }
+
+        #[ink::test]
+        fn arithmetic_near_max_errors_instead_of_panicking() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(u128::MAX, None, None, 0, None, Hash::default());
+            assert_eq!(erc20.mint(accounts.bob, 1), Err(Error::Overflow));
+            assert_eq!(erc20.transfer(accounts.bob, u128::MAX), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), u128::MAX);
+
+            // Break the supply invariant on purpose to reach the guards behind it.
+            erc20.balances.insert(accounts.alice, &1);
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.transfer(accounts.alice, u128::MAX),
+                Err(Error::Overflow)
+            );
+            assert_eq!(erc20.balance_of(accounts.bob), u128::MAX);
+
+            erc20.total_supply = 0;
+            erc20.total_shares = 0;
+            assert_eq!(erc20.burn(1), Err(Error::Underflow));
+            assert_eq!(erc20.balance_of(accounts.bob), u128::MAX);
+        }
+
+        #[ink::test]
+        fn psp22_messages_map_errors() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            assert_eq!(PSP22::total_supply(&erc20), 1000);
+
+            assert_eq!(
+                PSP22::transfer(&mut erc20, accounts.bob, 10, Vec::new()),
+                Ok(())
+            );
+            assert_eq!(PSP22::balance_of(&erc20, accounts.bob), 10);
+            assert_eq!(
+                PSP22::transfer(&mut erc20, accounts.bob, 991, Vec::new()),
+                Err(PSP22Error::InsufficientBalance)
+            );
+
+            assert_eq!(
+                PSP22::approve(&mut erc20, accounts.bob, 5),
+                Err(PSP22Error::Custom(String::from("SpenderNotContract")))
+            );
+            erc20.set_contract_spenders_only(false);
+            assert_eq!(PSP22::approve(&mut erc20, accounts.bob, 5), Ok(()));
+            assert_eq!(
+                PSP22::increase_allowance(&mut erc20, accounts.bob, 5),
+                Ok(())
+            );
+            assert_eq!(PSP22::allowance(&erc20, accounts.alice, accounts.bob), 10);
+            assert_eq!(
+                PSP22::decrease_allowance(&mut erc20, accounts.bob, 11),
+                Err(PSP22Error::InsufficientAllowance)
+            );
+
+            set_caller(accounts.bob);
+            assert_eq!(
+                PSP22::transfer_from(&mut erc20, accounts.alice, accounts.charlie, 11, Vec::new()),
+                Err(PSP22Error::InsufficientAllowance)
+            );
+            assert_eq!(
+                PSP22::transfer_from(&mut erc20, accounts.alice, accounts.charlie, 10, Vec::new()),
+                Ok(())
+            );
+            assert_eq!(
+                PSP22::transfer(&mut erc20, accounts.bob, 1, Vec::new()),
+                Ok(())
+            );
+
+            set_caller(accounts.charlie);
+            erc20.set_refuse_incoming(true);
+            set_caller(accounts.bob);
+            assert_eq!(
+                PSP22::transfer(&mut erc20, accounts.charlie, 1, Vec::new()),
+                Err(PSP22Error::Custom(String::from("RecipientRefused")))
+            );
+        }
+
+        #[ink::test]
+        fn transfer_and_call_skips_hook_for_plain_accounts() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            assert_eq!(
+                erc20.transfer_and_call(accounts.bob, 10, vec![1, 2, 3]),
+                Ok(())
+            );
+            assert_eq!(erc20.balance_of(accounts.bob), 10);
+            assert_transfer_event(
+                recorded_events().last().expect("event recorded"),
+                Some(accounts.alice),
+                Some(accounts.bob),
+                10,
+            );
+            assert_eq!(
+                erc20.transfer_and_call(accounts.bob, 991, Vec::new()),
+                Err(Error::BalanceTooLow)
+            );
+        }
+
+        /// Account controlled by the secp256k1 key `secret`.
+        fn ecdsa_account(secret: [u8; 32]) -> AccountId {
+            let secp = secp256k1::Secp256k1::new();
+            let secret = secp256k1::SecretKey::from_slice(&secret).expect("valid secret key");
+            let mut account = [0; 32];
+            ink::env::hash_bytes::<Blake2x256>(&secret.public_key(&secp).serialize(), &mut account);
+            AccountId::from(account)
+        }
+
+        /// Signs `hash` with the secp256k1 key `secret`, in the format
+        /// `ecdsa_recover` expects.
+        fn sign(secret: [u8; 32], hash: &[u8; 32]) -> [u8; 65] {
+            let secp = secp256k1::Secp256k1::new();
+            let secret = secp256k1::SecretKey::from_slice(&secret).expect("valid secret key");
+            let message = secp256k1::Message::from_slice(hash).expect("32 byte hash");
+            let (recovery_id, compact) = secp
+                .sign_ecdsa_recoverable(&message, &secret)
+                .serialize_compact();
+            let mut signature = [0; 65];
+            signature[..64].copy_from_slice(&compact);
+            signature[64] = recovery_id.to_i32() as u8;
+            signature
+        }
+
+        /// Signs a permit with the secp256k1 key `secret`, returning the
+        /// owner account derived from the key and the signature.
+        fn sign_permit(
+            erc20: &Erc20,
+            secret: [u8; 32],
+            spender: AccountId,
+            value: Balance,
+            deadline: Timestamp,
+        ) -> (AccountId, [u8; 65]) {
+            let owner = ecdsa_account(secret);
+            let hash = erc20.permit_hash(owner, spender, value, erc20.nonces(owner), deadline);
+            (owner, sign(secret, &hash))
+        }
+
+        #[ink::test]
+        fn permit_sets_allowance_once() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            let (owner, signature) = sign_permit(&erc20, [7; 32], accounts.bob, 50, 100);
+            assert_eq!(erc20.nonces(owner), 0);
+            // The spender is a plain account.
+            set_caller(owner);
+            erc20.set_contract_spenders_only(false);
+
+            set_caller(accounts.charlie);
+            assert_eq!(
+                erc20.permit(owner, accounts.charlie, 50, 100, signature),
+                Err(Error::InvalidSignature)
+            );
+            assert_eq!(
+                erc20.permit(owner, accounts.bob, 50, 100, signature),
+                Ok(())
+            );
+            assert_eq!(erc20.allowance(owner, accounts.bob), 50);
+            assert_eq!(erc20.nonces(owner), 1);
+            assert_approval_event(
+                recorded_events().last().expect("event recorded"),
+                owner,
+                accounts.bob,
+                50,
+            );
+
+            assert_eq!(
+                erc20.permit(owner, accounts.bob, 50, 100, signature),
+                Err(Error::InvalidSignature)
+            );
+        }
+
+        #[ink::test]
+        fn permit_rejects_expired_deadline() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            let (owner, signature) = sign_permit(&erc20, [7; 32], accounts.bob, 50, 100);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(101);
+            assert_eq!(
+                erc20.permit(owner, accounts.bob, 50, 100, signature),
+                Err(Error::SignatureExpired)
+            );
+            assert_eq!(erc20.nonces(owner), 0);
+        }
+
+        #[ink::test]
+        fn transfer_with_permit_settles_in_one_call() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            let (owner, signature) = sign_permit(&erc20, [9; 32], accounts.bob, 40, 100);
+            assert_eq!(erc20.transfer(owner, 100), Ok(()));
+
+            // The spender is a plain account, which the owner has to allow.
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.transfer_with_permit(owner, accounts.charlie, 40, 100, signature),
+                Err(Error::SpenderNotContract)
+            );
+            assert_eq!(erc20.nonces(owner), 0);
+            set_caller(owner);
+            erc20.set_contract_spenders_only(false);
+
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.transfer_with_permit(owner, accounts.charlie, 41, 100, signature),
+                Err(Error::InvalidSignature)
+            );
+            assert_eq!(
+                erc20.transfer_with_permit(owner, accounts.charlie, 40, 100, signature),
+                Ok(())
+            );
+            assert_eq!(erc20.balance_of(owner), 60);
+            assert_eq!(erc20.balance_of(accounts.charlie), 40);
+            assert_eq!(erc20.allowance(owner, accounts.bob), 0);
+            assert_eq!(erc20.nonces(owner), 1);
+            assert_transfer_event(
+                recorded_events().last().expect("event recorded"),
+                Some(owner),
+                Some(accounts.charlie),
+                40,
+            );
+        }
+
+        #[ink::test]
+        fn meta_tx_is_relayed_by_trusted_forwarder_only() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            let from = ecdsa_account([5; 32]);
+            assert_eq!(erc20.transfer(from, 100), Ok(()));
+            assert_eq!(erc20.set_trusted_forwarder(Some(accounts.bob)), Ok(()));
+            assert_eq!(erc20.trusted_forwarder(), Some(accounts.bob));
+
+            let hash = erc20.meta_tx_hash(from, accounts.charlie, 30, 0, 100);
+            let signature = sign([5; 32], &hash);
+            assert_eq!(
+                erc20.execute_meta_tx(from, accounts.charlie, 30, 100, signature),
+                Err(Error::NotTrustedForwarder)
+            );
+
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.execute_meta_tx(from, accounts.bob, 30, 100, signature),
+                Err(Error::InvalidSignature)
+            );
+            assert_eq!(
+                erc20.execute_meta_tx(from, accounts.charlie, 30, 100, signature),
+                Ok(())
+            );
+            assert_eq!(erc20.balance_of(from), 70);
+            assert_eq!(erc20.balance_of(accounts.charlie), 30);
+            assert_eq!(erc20.nonces(from), 1);
+            assert_eq!(
+                erc20.execute_meta_tx(from, accounts.charlie, 30, 100, signature),
+                Err(Error::InvalidSignature)
+            );
+
+            // A permit signature cannot be replayed as a transfer.
+            let (owner, permit) = sign_permit(&erc20, [5; 32], accounts.charlie, 30, 100);
+            assert_eq!(
+                erc20.execute_meta_tx(owner, accounts.charlie, 30, 100, permit),
+                Err(Error::InvalidSignature)
+            );
+        }
+
+        #[ink::test]
+        fn transfer_batch_is_all_or_nothing() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            let events_before = recorded_events().len();
+            assert_eq!(
+                erc20.transfer_batch(vec![(accounts.bob, 600), (accounts.charlie, 401)]),
+                Err(Error::BalanceTooLow)
+            );
+            set_caller(accounts.charlie);
+            erc20.set_refuse_incoming(true);
+            set_caller(accounts.alice);
+            assert_eq!(
+                erc20.transfer_batch(vec![(accounts.bob, 10), (accounts.charlie, 10)]),
+                Err(Error::RecipientRefused)
+            );
+            assert_eq!(erc20.balance_of(accounts.bob), 0);
+            assert_eq!(recorded_events().len(), events_before);
+
+            assert_eq!(
+                erc20.transfer_batch(vec![(accounts.bob, 600), (accounts.django, 400)]),
+                Ok(())
+            );
+            assert_eq!(erc20.balance_of(accounts.alice), 0);
+            assert_eq!(erc20.balance_of(accounts.bob), 600);
+            assert_eq!(erc20.balance_of(accounts.django), 400);
+            let emitted_events = recorded_events();
+            assert_eq!(emitted_events.len(), events_before + 2);
+            assert_transfer_event(
+                &emitted_events[events_before],
+                Some(accounts.alice),
+                Some(accounts.bob),
+                600,
+            );
+            assert_transfer_event(
+                &emitted_events[events_before + 1],
+                Some(accounts.alice),
+                Some(accounts.django),
+                400,
+            );
+
+            let oversized = vec![(accounts.bob, 0); MAX_BATCH_LEN + 1];
+            assert_eq!(erc20.transfer_batch(oversized), Err(Error::BatchTooLarge));
+        }
+
+        #[ink::test]
+        fn transfer_with_envelope_emits_envelope() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            assert_eq!(
+                erc20.transfer_with_envelope(accounts.bob, 10, vec![0; MAX_ENVELOPE_LEN + 1]),
+                Err(Error::EnvelopeTooLarge)
+            );
+
+            assert_eq!(
+                erc20.transfer_with_envelope(accounts.bob, 10, vec![0xAB; 48]),
+                Ok(())
+            );
+            assert_eq!(erc20.balance_of(accounts.bob), 10);
+            let emitted_events = recorded_events();
+            let [.., transfer, envelope] = emitted_events.as_slice() else {
+                panic!("expected two events");
+            };
+            assert_transfer_event(transfer, Some(accounts.alice), Some(accounts.bob), 10);
+            match decode_event(envelope) {
+                Event::TravelRuleEnvelope(TravelRuleEnvelope {
+                    from,
+                    to,
+                    value,
+                    envelope,
+                }) => {
+                    assert_eq!(from, accounts.alice);
+                    assert_eq!(to, accounts.bob);
+                    assert_eq!(value, 10);
+                    assert_eq!(envelope, vec![0xAB; 48]);
+                }
+                _ => panic!("expected a TravelRuleEnvelope event"),
+            }
+        }
+
+        #[ink::test]
+        fn batch_queries_follow_input_order() {
+            let accounts = accounts();
+            let erc20 = Scenario::new()
+                .with_balance(accounts.bob, 30)
+                .with_allowance(accounts.alice, accounts.charlie, 5)
+                .build();
+            assert_eq!(
+                erc20.balances_of(vec![accounts.bob, accounts.charlie, accounts.bob]),
+                vec![30, 0, 30]
+            );
+            assert_eq!(
+                erc20.allowances_of(vec![
+                    (accounts.alice, accounts.charlie),
+                    (accounts.charlie, accounts.alice),
+                ]),
+                vec![5, 0]
+            );
+            assert!(erc20.balances_of(Vec::new()).is_empty());
+        }
+
+        type Notification = (Option<AccountId>, Option<AccountId>, Balance);
+
+        /// Chain extension that records every notification and reports
+        /// failure through its status code.
+        struct TransferNotifications(std::rc::Rc<std::cell::RefCell<Vec<Notification>>>);
+
+        impl ink::env::test::ChainExtension for TransferNotifications {
+            fn func_id(&self) -> u32 {
+                0x7E57
+            }
+
+            fn call(&mut self, mut input: &[u8], _output: &mut Vec<u8>) -> u32 {
+                let input = <Vec<u8> as scale::Decode>::decode(&mut input).expect("input");
+                let notification =
+                    scale::Decode::decode(&mut input.as_slice()).expect("notification");
+                self.0.borrow_mut().push(notification);
+                1
+            }
+        }
+
+        #[ink::test]
+        fn transfers_notify_configured_extension() {
+            let accounts = accounts();
+            let notifications = std::rc::Rc::default();
+            ink::env::test::register_chain_extension(TransferNotifications(std::rc::Rc::clone(
+                &notifications,
+            )));
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            assert_eq!(erc20.transfer(accounts.bob, 1), Ok(()));
+            assert!(notifications.borrow().is_empty());
+
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.set_transfer_extension(Some(0x7E57)),
+                Err(Error::NotOwner)
+            );
+            set_caller(accounts.alice);
+            assert_eq!(erc20.set_transfer_extension(Some(0x7E57)), Ok(()));
+            assert_eq!(erc20.transfer_extension(), Some(0x7E57));
+
+            assert_eq!(erc20.transfer(accounts.bob, 10), Ok(()));
+            assert_eq!(erc20.mint(accounts.bob, 5), Ok(()));
+            assert_eq!(erc20.burn(3), Ok(()));
+            assert_eq!(
+                *notifications.borrow(),
+                vec![
+                    (Some(accounts.alice), Some(accounts.bob), 10),
+                    (None, Some(accounts.bob), 5),
+                    (Some(accounts.alice), None, 3),
+                ]
+            );
+        }
+
+        /// Builds the airdrop tree over `leaves`, returning its root and the
+        /// proof of every leaf. A node without a sibling moves up unchanged.
+        fn merkle_tree(leaves: &[Hash]) -> (Hash, Vec<Vec<Hash>>) {
+            let mut proofs = vec![Vec::new(); leaves.len()];
+            // Leaves below each node of the current level.
+            let mut level: Vec<(Hash, Vec<usize>)> = leaves
+                .iter()
+                .enumerate()
+                .map(|(index, leaf)| (*leaf, vec![index]))
+                .collect();
+            while level.len() > 1 {
+                level = level
+                    .chunks(2)
+                    .map(|pair| match pair {
+                        [(left, left_leaves), (right, right_leaves)] => {
+                            for &leaf in left_leaves {
+                                proofs[leaf].push(*right);
+                            }
+                            for &leaf in right_leaves {
+                                proofs[leaf].push(*left);
+                            }
+                            let leaves = [left_leaves.as_slice(), right_leaves].concat();
+                            (Erc20::merkle_parent(left, right), leaves)
+                        }
+                        [single] => single.clone(),
+                        _ => unreachable!("chunks of two"),
+                    })
+                    .collect();
+            }
+            (level[0].0, proofs)
+        }
+
+        #[ink::test]
+        fn airdrop_claims_verify_proofs_once() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            let allocations = [
+                (accounts.bob, 100),
+                (accounts.charlie, 200),
+                (accounts.django, 300),
+            ];
+            let leaves: Vec<Hash> = allocations
+                .iter()
+                .enumerate()
+                .map(|(index, (account, amount))| {
+                    Erc20::merkle_leaf(index as u32, *account, *amount)
+                })
+                .collect();
+            let (root, proofs) = merkle_tree(&leaves);
+
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.claim(0, 100, proofs[0].clone()),
+                Err(Error::InvalidProof)
+            );
+            assert_eq!(erc20.set_merkle_root(root), Err(Error::NotOwner));
+            set_caller(accounts.alice);
+            assert_eq!(erc20.set_merkle_root(root), Ok(()));
+            assert_eq!(erc20.merkle_root(), Some(root));
+
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.claim(0, 101, proofs[0].clone()),
+                Err(Error::InvalidProof)
+            );
+            assert_eq!(
+                erc20.claim(1, 200, proofs[1].clone()),
+                Err(Error::InvalidProof)
+            );
+            assert_eq!(erc20.claim(0, 100, proofs[0].clone()), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 100);
+            assert_eq!(erc20.total_supply(), 1100);
+            assert!(erc20.is_claimed(0));
+            assert!(!erc20.is_claimed(1));
+            match decode_event(recorded_events().last().expect("event recorded")) {
+                Event::Claimed(Claimed {
+                    index,
+                    account,
+                    amount,
+                }) => {
+                    assert_eq!(index, 0);
+                    assert_eq!(account, accounts.bob);
+                    assert_eq!(amount, 100);
+                }
+                _ => panic!("expected a Claimed event"),
+            }
+            assert_eq!(
+                erc20.claim(0, 100, proofs[0].clone()),
+                Err(Error::AlreadyClaimed)
+            );
+
+            set_caller(accounts.django);
+            erc20.set_refuse_incoming(true);
+            assert_eq!(
+                erc20.claim(2, 300, proofs[2].clone()),
+                Err(Error::RecipientRefused)
+            );
+            assert!(!erc20.is_claimed(2));
+            erc20.set_refuse_incoming(false);
+            assert_eq!(erc20.claim(2, 300, proofs[2].clone()), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.django), 300);
+        }
+
+        #[ink::test]
+        fn vesting_releases_linearly_after_cliff() {
+            let accounts = accounts();
+            let contract = contract_account();
+            let mut erc20 = Scenario::new()
+                .with_balance(accounts.alice, 1000)
+                .deployed_at(contract)
+                .build();
+            assert_eq!(
+                erc20.create_vesting(accounts.bob, 400, 100, 50, 0),
+                Err(Error::InvalidSchedule)
+            );
+            assert_eq!(
+                erc20.create_vesting(accounts.bob, 400, 100, 50, 200),
+                Ok(())
+            );
+            assert_eq!(
+                erc20.create_vesting(accounts.bob, 1, 100, 50, 200),
+                Err(Error::GrantExists)
+            );
+            assert_eq!(erc20.balance_of(accounts.alice), 600);
+            assert_eq!(erc20.balance_of(contract), 400);
+
+            assert_eq!(erc20.vested_amount(accounts.bob, 149), 0);
+            assert_eq!(erc20.vested_amount(accounts.bob, 150), 100);
+            assert_eq!(erc20.vested_amount(accounts.bob, 300), 400);
+
+            set_caller(accounts.bob);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(120);
+            assert_eq!(erc20.release(), Ok(0));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(200);
+            assert_eq!(erc20.releasable(accounts.bob), 200);
+            assert_eq!(erc20.release(), Ok(200));
+            assert_eq!(erc20.releasable(accounts.bob), 0);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1000);
+            assert_eq!(erc20.release(), Ok(200));
+            assert_eq!(erc20.balance_of(accounts.bob), 400);
+            assert_eq!(erc20.balance_of(contract), 0);
+            match decode_event(recorded_events().last().expect("event recorded")) {
+                Event::TokensReleased(TokensReleased {
+                    beneficiary,
+                    amount,
+                }) => {
+                    assert_eq!(beneficiary, accounts.bob);
+                    assert_eq!(amount, 200);
+                }
+                _ => panic!("expected a TokensReleased event"),
+            }
+
+            set_caller(accounts.charlie);
+            assert_eq!(erc20.release(), Err(Error::NoGrant));
+        }
+
+        #[ink::test]
+        fn timelock_delays_admin_operations() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            let salt = Hash::from([0; 32]);
+            assert_eq!(erc20.set_min_delay(2), Ok(()));
+            assert_eq!(erc20.min_delay(), 2);
+            assert_eq!(erc20.add_minter(accounts.bob), Err(Error::Timelocked));
+            assert_eq!(erc20.set_min_delay(0), Err(Error::Timelocked));
+
+            let operation = AdminOperation::GrantRole(MINTER, accounts.bob);
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.schedule(operation.clone(), salt),
+                Err(Error::NotOwner)
+            );
+            set_caller(accounts.alice);
+            let id = erc20.schedule(operation.clone(), salt).expect("scheduled");
+            assert_eq!(
+                erc20.schedule(operation.clone(), salt),
+                Err(Error::OperationExists)
+            );
+            assert_eq!(erc20.operation_ready_at(id), Some(2));
+
+            match decode_event(recorded_events().last().expect("event recorded")) {
+                Event::OperationScheduled(event) => assert_eq!(event.caller, accounts.alice),
+                _ => panic!("expected an OperationScheduled event"),
+            }
+
+            set_caller(accounts.charlie);
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(
+                erc20.execute(operation.clone(), salt),
+                Err(Error::OperationNotReady)
+            );
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.execute(operation.clone(), salt), Ok(()));
+            assert!(erc20.is_minter(accounts.bob));
+            let emitted_events = recorded_events();
+            let [.., granted, _executed] = emitted_events.as_slice() else {
+                panic!("expected two events");
+            };
+            match decode_event(granted) {
+                // The event names the owner who scheduled the grant, not
+                // charlie who executed it.
+                Event::RoleGranted(RoleGranted { sender, .. }) => {
+                    assert_eq!(sender, accounts.alice)
+                }
+                _ => panic!("expected a RoleGranted event"),
+            }
+            assert_eq!(erc20.operation_ready_at(id), None);
+            assert_eq!(erc20.execute(operation, salt), Err(Error::UnknownOperation));
+
+            set_caller(accounts.alice);
+            let operation = AdminOperation::SetMinDelay(0);
+            let id = erc20.schedule(operation.clone(), salt).expect("scheduled");
+            assert_eq!(erc20.cancel(id), Ok(()));
+            assert_eq!(erc20.cancel(id), Err(Error::UnknownOperation));
+            assert_eq!(erc20.execute(operation, salt), Err(Error::UnknownOperation));
+            assert_eq!(erc20.min_delay(), 2);
+        }
+
+        #[ink::test]
+        fn scheduled_operations_lapse_with_ownership() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            let salt = Hash::from([0; 32]);
+            assert_eq!(erc20.set_min_delay(1), Ok(()));
+            let operation = AdminOperation::GrantRole(MINTER, accounts.bob);
+            erc20.schedule(operation.clone(), salt).expect("scheduled");
+
+            assert_eq!(erc20.transfer_ownership(accounts.charlie), Ok(()));
+            set_caller(accounts.charlie);
+            assert_eq!(erc20.accept_ownership(), Ok(()));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.execute(operation.clone(), salt), Err(Error::NotOwner));
 
-            let emitted_events = ink::env::test::recorded_events().collect::<Vec<_>>();
-            let event = emitted_events[0].clone();
-            let decoded =
-                <Event as scale::Decode>::decode(&mut &event.data[..]).expect("decoded error");
-            match decoded {
-                Event::Transfer(Transfer { from, to, value }) => {
-                    assert!(from.is_none(), "mint from error");
-                    assert_eq!(to, Some(accounts.alice));
-                    assert_eq!(value, 1000);
+            // The new owner's own operations run until they renounce.
+            let id = erc20
+                .schedule(operation.clone(), Hash::from([1; 32]))
+                .expect("scheduled");
+            let renounced = AdminOperation::GrantRole(MINTER, accounts.django);
+            erc20.schedule(renounced.clone(), salt).expect("scheduled");
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.execute(operation, Hash::from([1; 32])), Ok(()));
+            assert!(erc20.is_minter(accounts.bob));
+            assert_eq!(erc20.operation_ready_at(id), None);
+
+            assert_eq!(erc20.renounce_ownership(), Ok(()));
+            assert_eq!(erc20.execute(renounced, salt), Err(Error::NotOwner));
+            assert!(!erc20.is_minter(accounts.django));
+        }
+
+        #[ink::test]
+        fn transfer_fee_goes_to_collector() {
+            let accounts = accounts();
+            let mut erc20 = Scenario::new()
+                .with_balance(accounts.alice, 1000)
+                .deployed_at(contract_account())
+                .build();
+            assert_eq!(erc20.set_fee_bps(10_001), Err(Error::InvalidFee));
+            assert_eq!(erc20.set_fee_bps(250), Ok(()));
+            assert_eq!(erc20.transfer_fee(), 250);
+
+            // No fee is charged until there is a collector.
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 100);
+
+            set_caller(accounts.bob);
+            assert_eq!(
+                erc20.set_fee_collector(Some(accounts.django)),
+                Err(Error::NotOwner)
+            );
+            set_caller(accounts.alice);
+            assert_eq!(erc20.set_fee_collector(Some(accounts.django)), Ok(()));
+            assert_eq!(erc20.fee_collector(), Some(accounts.django));
+            assert_eq!(
+                erc20.simulate_transfer(accounts.alice, accounts.bob, 200),
+                TransferSimulation {
+                    net: 195,
+                    fee: 5,
+                    burned: 0,
+                    blocked_by: None,
+                }
+            );
+
+            assert_eq!(erc20.transfer(accounts.bob, 200), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.alice), 700);
+            assert_eq!(erc20.balance_of(accounts.bob), 295);
+            assert_eq!(erc20.balance_of(accounts.django), 5);
+            assert_eq!(erc20.total_supply(), 1000);
+            let emitted_events = recorded_events();
+            let [.., net, fee] = emitted_events.as_slice() else {
+                panic!("expected two events");
+            };
+            assert_transfer_event(net, Some(accounts.alice), Some(accounts.bob), 195);
+            assert_transfer_event(fee, Some(accounts.alice), Some(accounts.django), 5);
+
+            // A recipient that collects fees keeps the whole transfer.
+            assert_eq!(erc20.set_fee_collector(Some(accounts.bob)), Ok(()));
+            assert_eq!(erc20.transfer(accounts.bob, 400), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.alice), 300);
+            assert_eq!(erc20.balance_of(accounts.bob), 695);
+        }
+
+        #[ink::test]
+        fn fee_exempt_accounts_pay_no_fee() {
+            let accounts = accounts();
+            let mut erc20 = Scenario::new()
+                .with_balance(accounts.alice, 1000)
+                .deployed_at(contract_account())
+                .build();
+            assert_eq!(erc20.set_fee_bps(1000), Ok(()));
+            assert_eq!(erc20.set_fee_collector(Some(accounts.django)), Ok(()));
+
+            set_caller(accounts.bob);
+            assert_eq!(erc20.add_fee_exempt(accounts.bob), Err(Error::NotOwner));
+            set_caller(accounts.alice);
+            assert_eq!(erc20.add_fee_exempt(accounts.bob), Ok(()));
+            assert!(erc20.is_fee_exempt(accounts.bob));
+
+            // Exemption covers both sending and receiving.
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+            set_caller(accounts.bob);
+            assert_eq!(erc20.transfer(accounts.charlie, 50), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.charlie), 50);
+            assert_eq!(erc20.balance_of(accounts.django), 0);
+
+            set_caller(accounts.alice);
+            assert_eq!(erc20.remove_fee_exempt(accounts.bob), Ok(()));
+            assert!(!erc20.is_fee_exempt(accounts.bob));
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 140);
+            assert_eq!(erc20.balance_of(accounts.django), 10);
+        }
+
+        #[ink::test]
+        fn fee_parameter_changes_emit_events() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            assert_eq!(erc20.set_fee_bps(250), Ok(()));
+            assert_eq!(erc20.set_fee_collector(Some(accounts.django)), Ok(()));
+            assert_eq!(erc20.set_burn_bps(100), Ok(()));
+            assert_eq!(erc20.add_fee_exempt(accounts.bob), Ok(()));
+            let events_before = recorded_events().len();
+            assert_eq!(erc20.add_fee_exempt(accounts.bob), Ok(()));
+            assert_eq!(recorded_events().len(), events_before);
+
+            let emitted_events = recorded_events();
+            let [.., fee, collector, burn, exemption] = emitted_events.as_slice() else {
+                panic!("expected four events");
+            };
+            match decode_event(fee) {
+                Event::FeeBpsChanged(event) => {
+                    assert_eq!((event.sender, event.fee_bps), (accounts.alice, 250))
+                }
+                _ => panic!("expected a FeeBpsChanged event"),
+            }
+            match decode_event(collector) {
+                Event::FeeCollectorChanged(event) => {
+                    assert_eq!(event.sender, accounts.alice);
+                    assert_eq!(event.collector, Some(accounts.django));
+                }
+                _ => panic!("expected a FeeCollectorChanged event"),
+            }
+            match decode_event(burn) {
+                Event::BurnBpsChanged(event) => {
+                    assert_eq!((event.sender, event.burn_bps), (accounts.alice, 100))
                 }
-                _ => panic!("Expecting a Transfer event"),
+                _ => panic!("expected a BurnBpsChanged event"),
+            }
+            match decode_event(exemption) {
+                Event::FeeExemptionChanged(event) => {
+                    assert_eq!(
+                        (event.sender, event.account),
+                        (accounts.alice, accounts.bob)
+                    );
+                    assert!(event.exempt);
+                }
+                _ => panic!("expected a FeeExemptionChanged event"),
+            }
+
+            // A timelocked change names the owner who scheduled it.
+            let salt = Hash::from([0; 32]);
+            assert_eq!(erc20.set_min_delay(1), Ok(()));
+            let operation = AdminOperation::RemoveFeeExempt(accounts.bob);
+            erc20.schedule(operation.clone(), salt).expect("scheduled");
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            set_caller(accounts.charlie);
+            assert_eq!(erc20.execute(operation, salt), Ok(()));
+            let emitted_events = recorded_events();
+            let [.., exemption, _executed] = emitted_events.as_slice() else {
+                panic!("expected two events");
+            };
+            match decode_event(exemption) {
+                Event::FeeExemptionChanged(event) => {
+                    assert_eq!(event.sender, accounts.alice);
+                    assert!(!event.exempt);
+                }
+                _ => panic!("expected a FeeExemptionChanged event"),
             }
         }
 
         #[ink::test]
-        fn transfer_should_work() {
-            let mut erc20 = Erc20::new(1000);
-            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            let res = erc20.transfer(accounts.bob, 12);
-            assert_eq!(res, Ok(()));
-            assert_eq!(erc20.balance_of(accounts.alice), 1000 - 12);
-            assert_eq!(erc20.balance_of(accounts.bob), 12);
+        fn transfers_burn_a_share_of_the_value() {
+            let accounts = accounts();
+            let mut erc20 = Scenario::new()
+                .with_balance(accounts.alice, 1000)
+                .deployed_at(contract_account())
+                .build();
+            assert_eq!(erc20.set_burn_bps(MAX_BURN_BPS + 1), Err(Error::InvalidFee));
+            assert_eq!(erc20.set_burn_bps(500), Ok(()));
+            assert_eq!(erc20.burn_bps(), 500);
+            assert_eq!(erc20.set_fee_bps(9_600), Err(Error::InvalidFee));
+
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.alice), 900);
+            assert_eq!(erc20.balance_of(accounts.bob), 95);
+            assert_eq!(erc20.total_supply(), 995);
+            let emitted_events = recorded_events();
+            let [.., net, burned] = emitted_events.as_slice() else {
+                panic!("expected two events");
+            };
+            assert_transfer_event(net, Some(accounts.alice), Some(accounts.bob), 95);
+            assert_transfer_event(burned, Some(accounts.alice), None, 5);
+
+            // Burning comes on top of the fee.
+            assert_eq!(erc20.set_fee_bps(1_000), Ok(()));
+            assert_eq!(erc20.set_fee_collector(Some(accounts.django)), Ok(()));
+            assert_eq!(
+                erc20.simulate_transfer(accounts.alice, accounts.bob, 200),
+                TransferSimulation {
+                    net: 170,
+                    fee: 20,
+                    burned: 10,
+                    blocked_by: None,
+                }
+            );
+            assert_eq!(erc20.transfer(accounts.bob, 200), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.bob), 265);
+            assert_eq!(erc20.balance_of(accounts.django), 20);
+            assert_eq!(erc20.total_supply(), 985);
         }
 
         #[ink::test]
-        fn invalid_transfer_should_fail() {
-            let mut erc20 = Erc20::new(1000);
-            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
-            let res = erc20.transfer(accounts.charlie, 12);
-            assert_eq!(res, Err(Error::BalanceTooLow));
-            /* let res = erc20.transfer(accounts.bob, 1001);
-            assert_eq!(res, Err(Error::BalanceTooLow));
+        fn fees_and_burns_are_totalled_per_account() {
+            let accounts = accounts();
+            let mut erc20 = Scenario::new()
+                .with_balance(accounts.alice, 1000)
+                .deployed_at(contract_account())
+                .build();
+            assert_eq!(erc20.set_fee_bps(1_000), Ok(()));
+            assert_eq!(erc20.set_fee_collector(Some(accounts.django)), Ok(()));
+            assert_eq!(erc20.set_burn_bps(500), Ok(()));
+
+            assert_eq!(erc20.transfer(accounts.bob, 200), Ok(()));
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+            assert_eq!(erc20.fees_paid(accounts.alice), 30);
+            assert_eq!(erc20.burned_by(accounts.alice), 15);
+            assert_eq!(erc20.fees_paid(accounts.bob), 0);
+            assert_eq!(erc20.burned_by(accounts.bob), 0);
+
+            // Exempt transfers are not charged; explicit burns count too.
+            assert_eq!(erc20.add_fee_exempt(accounts.charlie), Ok(()));
+            assert_eq!(erc20.transfer(accounts.charlie, 100), Ok(()));
+            assert_eq!(erc20.burn(10), Ok(()));
+            assert_eq!(erc20.fees_paid(accounts.alice), 30);
+            assert_eq!(erc20.burned_by(accounts.alice), 25);
+
+            set_caller(accounts.bob);
+            assert_eq!(erc20.burn(5), Ok(()));
+            assert_eq!(erc20.burned_by(accounts.bob), 5);
+        }
+
+        #[ink::test]
+        fn client_encodes_calls_and_decodes_outputs() {
+            use crate::client::{self, TraitCallBuilder};
+            use scale::Encode;
+
+            let accounts = accounts();
+            let mut token = client::at(accounts.django);
+
+            let params = token.call_mut().transfer(accounts.bob, 100).params();
+            assert_eq!(*params.callee(), accounts.django);
+            let data = client::call_data(&params);
+            assert_eq!(data[..4], [0x84, 0xA1, 0x5D, 0xA1]);
+            assert_eq!(data[4..], (accounts.bob, 100u128).encode());
+            let output = Ok::<Result<()>, ink::LangError>(Err(Error::BalanceTooLow)).encode();
+            assert_eq!(
+                client::decode_output(&params, &output),
+                Ok(Ok(Err(Error::BalanceTooLow)))
+            );
+
+            let params = token.call().balance_of(accounts.bob).params();
+            let output = Ok::<Balance, ink::LangError>(42).encode();
+            assert_eq!(client::decode_output(&params, &output), Ok(Ok(42)));
+        }
+
+        #[ink::test]
+        fn rebase_scales_balances_through_shares() {
+            let accounts = accounts();
+            let mut erc20 = Scenario::new()
+                .with_balance(accounts.alice, 600)
+                .with_balance(accounts.bob, 300)
+                .with_balance(accounts.charlie, 100)
+                .build();
+
+            set_caller(accounts.bob);
+            assert_eq!(erc20.rebase(1000), Err(Error::NotRebaser));
+            set_caller(accounts.alice);
+            assert_eq!(erc20.rebase(-1000), Err(Error::InvalidRebase));
+            assert_eq!(erc20.rebase(-1001), Err(Error::Underflow));
+
+            assert_eq!(erc20.rebase(1000), Ok(()));
+            assert_eq!(erc20.total_supply(), 2000);
+            assert_eq!(erc20.total_shares(), 1000);
+            assert_eq!(erc20.balance_of(accounts.alice), 1200);
+            assert_eq!(erc20.balance_of(accounts.bob), 600);
+            assert_eq!(erc20.shares_of(accounts.bob), 300);
+
+            // Transfers move shares worth the requested value.
+            set_caller(accounts.bob);
+            assert_eq!(erc20.transfer(accounts.django, 100), Ok(()));
+            assert_eq!(erc20.shares_of(accounts.django), 50);
+            assert_eq!(erc20.balance_of(accounts.django), 100);
+            assert_eq!(erc20.balance_of(accounts.bob), 500);
+
+            // A rebaser contracts the supply, and minting issues new shares.
+            set_caller(accounts.alice);
+            assert_eq!(erc20.grant_role(REBASER, accounts.eve), Ok(()));
+            set_caller(accounts.eve);
+            assert_eq!(erc20.rebase(-1500), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.alice), 300);
+            assert_eq!(erc20.balance_of(accounts.django), 25);
+            set_caller(accounts.alice);
+            assert_eq!(erc20.mint(accounts.charlie, 50), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.charlie), 100);
+            assert_eq!(erc20.total_supply(), 550);
+            assert_eq!(erc20.total_shares(), 1100);
+
+            let emitted_events = recorded_events();
+            let rebased = emitted_events
+                .iter()
+                .filter_map(|event| match decode_event(event) {
+                    Event::Rebased(Rebased {
+                        previous_supply,
+                        total_supply,
+                        ..
+                    }) => Some((previous_supply, total_supply)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(rebased, [(1000, 2000), (2000, 500)]);
+        }
+
+        #[test]
+        fn mul_div_uses_the_full_product() {
+            assert_eq!(Erc20::mul_div(6, 7, 4), Some(10));
+            assert_eq!(
+                Erc20::mul_div(u128::MAX, u128::MAX, u128::MAX),
+                Some(u128::MAX)
+            );
+            assert_eq!(Erc20::mul_div(u128::MAX, 3, 6), Some(u128::MAX / 2));
+            assert_eq!(Erc20::mul_div(u128::MAX, 2, 1), None);
+            assert_eq!(Erc20::mul_div(1, 1, 0), None);
+        }
+
+        #[cfg(feature = "subxt")]
+        #[test]
+        fn client_tx_builds_contract_call_values() {
+            use crate::client::{self, tx, TraitCallBuilder};
+            use subxt::dynamic::Value;
+
+            let token = AccountId::from([0x01; 32]);
+            let to = AccountId::from([0x02; 32]);
+            let limits = tx::Limits {
+                ref_time: 1_000_000,
+                proof_size: 10_000,
+                storage_deposit: None,
+            };
+            let values = tx::transfer(token, to, 100, limits);
+            let params = client::at(token).call_mut().transfer(to, 100).params();
+            assert_eq!(
+                values,
+                [
+                    Value::unnamed_variant("Id", [Value::from_bytes(token)]),
+                    Value::u128(0),
+                    Value::named_composite([
+                        ("ref_time", Value::u128(1_000_000)),
+                        ("proof_size", Value::u128(10_000)),
+                    ]),
+                    Value::unnamed_variant("None", []),
+                    Value::from_bytes(client::call_data(&params)),
+                ]
+            );
+            let payload = tx::payload(values);
+            assert_eq!(
+                (payload.pallet_name(), payload.call_name()),
+                ("Contracts", "call")
+            );
+        }
+
+        #[ink::test]
+        fn snapshots_record_historical_balances() {
+            let accounts = accounts();
+            let mut erc20 = Scenario::new()
+                .with_balance(accounts.alice, 600)
+                .with_balance(accounts.bob, 400)
+                .build();
+
+            assert_eq!(erc20.snapshot(), Err(Error::NotSnapshotter));
+            assert_eq!(erc20.grant_role(SNAPSHOTTER, accounts.alice), Ok(()));
+            assert_eq!(
+                erc20.balance_of_at(accounts.alice, 1),
+                Err(Error::UnknownSnapshot)
+            );
+            assert_eq!(erc20.snapshot(), Ok(1));
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+            assert_eq!(erc20.snapshot(), Ok(2));
+            assert_eq!(erc20.snapshot(), Ok(3));
+            assert_eq!(erc20.burn(100), Ok(()));
+            assert_eq!(erc20.mint(accounts.charlie, 50), Ok(()));
+            assert_eq!(erc20.snapshot(), Ok(4));
+            assert_eq!(erc20.rebase(950), Ok(()));
+            assert_eq!(erc20.current_snapshot(), 4);
+
+            let history = |account| {
+                (1..=4)
+                    .map(|id| erc20.balance_of_at(account, id).unwrap())
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(history(accounts.alice), [600, 400, 400, 300]);
+            assert_eq!(history(accounts.bob), [400, 600, 600, 600]);
+            assert_eq!(history(accounts.charlie), [0, 0, 0, 50]);
+            let supplies = (1..=4)
+                .map(|id| erc20.total_supply_at(id).unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(supplies, [1000, 1000, 1000, 950]);
+            assert_eq!(erc20.balance_of(accounts.alice), 600);
+            assert_eq!(erc20.total_supply_at(5), Err(Error::UnknownSnapshot));
+        }
+
+        #[ink::test]
+        fn delegated_votes_follow_balances_over_time() {
+            let accounts = accounts();
+            let mut erc20 = Scenario::new()
+                .with_balance(accounts.alice, 600)
+                .with_balance(accounts.bob, 400)
+                .build();
+
+            // Block 0: alice self-delegates, bob delegates to charlie.
+            assert_eq!(erc20.delegate(accounts.alice), Ok(()));
+            set_caller(accounts.bob);
+            assert_eq!(erc20.delegate(accounts.charlie), Ok(()));
+            assert_eq!(erc20.delegates(accounts.bob), Some(accounts.charlie));
+            assert_eq!(erc20.get_votes(accounts.alice), 600);
+            assert_eq!(erc20.get_votes(accounts.charlie), 400);
+            assert_eq!(
+                erc20.get_past_votes(accounts.alice, 0),
+                Err(Error::FutureLookup)
+            );
+
+            // Block 1: transfers, mints and burns move delegated power.
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            set_caller(accounts.alice);
+            assert_eq!(erc20.transfer(accounts.bob, 100), Ok(()));
+            assert_eq!(erc20.mint(accounts.bob, 50), Ok(()));
+            assert_eq!(erc20.burn(200), Ok(()));
+            assert_eq!(erc20.get_votes(accounts.alice), 300);
+            assert_eq!(erc20.get_votes(accounts.charlie), 550);
+
+            // Block 2: bob moves his votes to alice.
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            set_caller(accounts.bob);
+            assert_eq!(erc20.delegate(accounts.alice), Ok(()));
+            assert_eq!(erc20.get_votes(accounts.alice), 850);
+            assert_eq!(erc20.get_votes(accounts.charlie), 0);
+
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            let past = |account| {
+                (0..3)
+                    .map(|block| erc20.get_past_votes(account, block).unwrap())
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(past(accounts.alice), [600, 300, 850]);
+            assert_eq!(past(accounts.charlie), [400, 550, 0]);
+            assert_eq!(past(accounts.django), [0, 0, 0]);
+
+            let emitted_events = recorded_events();
+            let changes = emitted_events
+                .iter()
+                .filter(|event| matches!(decode_event(event), Event::DelegateChanged(_)))
+                .count();
+            assert_eq!(changes, 3);
+        }
+
+        #[cfg(feature = "telemetry")]
+        #[ink::test]
+        fn state_changes_emit_call_trace() {
+            use crate::testing::call_traces;
+
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            set_caller(accounts.bob);
+            erc20.set_refuse_incoming(false);
+
+            let traces = call_traces();
+            let [.., trace] = traces.as_slice() else {
+                panic!("no call traced");
+            };
+            assert_eq!(trace.caller, accounts.bob);
+            assert_eq!(trace.caller_code_hash, None);
+            assert_eq!(trace.call_depth, Some(0));
+        }
+
+        #[ink::test]
+        fn rebase_scales_escrowed_tokens() {
+            let accounts = accounts();
+            let contract = contract_account();
+            let mut erc20 = Scenario::new()
+                .with_balance(accounts.alice, 1000)
+                .deployed_at(contract)
+                .build();
+            assert_eq!(erc20.create_vesting(accounts.bob, 400, 100, 0, 100), Ok(()));
+            assert_eq!(erc20.transfer(accounts.charlie, 10), Ok(()));
+
+            // An outside holder's lock cannot hold up the rebase.
+            set_caller(accounts.charlie);
+            let id = erc20
+                .lock_tokens(contract, 1, Timestamp::MAX)
+                .expect("locked");
+            set_caller(accounts.alice);
+            assert_eq!(erc20.rebase(1000), Ok(()));
+            assert_eq!(erc20.balance_of(accounts.alice), 1180);
+            assert_eq!(erc20.total_locked(contract), 2);
+            assert_eq!(erc20.token_lock(id).map(|lock| lock.amount), Some(2));
+
+            // The grant doubled along with every balance.
+            set_caller(accounts.bob);
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(150);
+            assert_eq!(erc20.releasable(accounts.bob), 400);
+            assert_eq!(erc20.release(), Ok(400));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(200);
+            assert_eq!(erc20.release(), Ok(400));
+            assert_eq!(erc20.balance_of(accounts.bob), 800);
+            assert_eq!(erc20.balance_of(contract), 2);
+        }
+
+        #[ink::test]
+        fn owner_rebase_goes_through_the_timelock() {
+            let accounts = accounts();
+            let mut erc20 = Erc20::new(1000, None, None, 0, None, Hash::default());
+            let salt = Hash::from([0; 32]);
+            assert_eq!(erc20.set_min_delay(1), Ok(()));
+            assert_eq!(erc20.rebase(1000), Err(Error::Timelocked));
+
+            let operation = AdminOperation::Rebase(1000);
+            erc20.schedule(operation.clone(), salt).expect("scheduled");
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.execute(operation, salt), Ok(()));
+            assert_eq!(erc20.total_supply(), 2000);
+
+            // A rebaser oracle is not held up by the timelock.
+            let operation = AdminOperation::GrantRole(REBASER, accounts.eve);
+            erc20.schedule(operation.clone(), salt).expect("scheduled");
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(erc20.execute(operation, salt), Ok(()));
+            set_caller(accounts.eve);
+            assert_eq!(erc20.rebase(-1000), Ok(()));
             assert_eq!(erc20.balance_of(accounts.alice), 1000);
-            assert_eq!(erc20.balance_of(accounts.bob), 0); */
+        }
+
+        #[ink::test]
+        fn domain_separator_depends_on_chain() {
+            let chain_id = Hash::from([0x01; 32]);
+            let first = Erc20::new(1000, None, None, 0, None, Hash::default());
+            let second = Erc20::new(1000, None, None, 0, None, chain_id);
+            assert_eq!(second.chain_id(), chain_id);
+            assert_eq!(first.deployment_id(), second.deployment_id());
+            assert_ne!(first.domain_separator(), second.domain_separator());
         }
     }
 
@@ -198,7 +4901,7 @@ mod erc20 {
         #[ink_e2e::test]
         async fn e2e_transfer(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
             let total_supply = 1000;
-            let constructor = Erc20Ref::new(total_supply);
+            let constructor = Erc20Ref::new(total_supply, None, None, 0, None, Hash::default());
             let contract_acc_id = client
                 .instantiate("erc20", &ink_e2e::alice(), constructor, 0, None)
                 .await
@@ -207,24 +4910,578 @@ mod erc20 {
             let alice_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
             let bob_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
 
-            let transfer_msg = build_message::<Erc20Ref>(
-                contract_acc_id.clone(),
-            ).call(|erc20| erc20.transfer(bob_acc.clone(), 2));
+            let transfer_msg = build_message::<Erc20Ref>(contract_acc_id.clone())
+                .call(|erc20| erc20.transfer(bob_acc.clone(), 2));
 
-            let res = client.call(
-                &ink_e2e::alice(), transfer_msg, 0, None
-            ).await;
+            let res = client.call(&ink_e2e::alice(), transfer_msg, 0, None).await;
 
-            let balance_of_msg = build_message::<Erc20Ref>(
-                contract_acc_id.clone(),
-            ).call(|erc20| erc20.balance_of(alice_acc.clone()));
-            let balance_of_alice = client.call_dry_run(
-                &ink_e2e::alice(), &balance_of_msg, 0, None
-            ).await;
+            let balance_of_msg = build_message::<Erc20Ref>(contract_acc_id.clone())
+                .call(|erc20| erc20.balance_of(alice_acc.clone()));
+            let balance_of_alice = client
+                .call_dry_run(&ink_e2e::alice(), &balance_of_msg, 0, None)
+                .await;
 
             assert_eq!(balance_of_alice.return_value(), 998);
 
             Ok(())
         }
+
+        #[ink_e2e::test]
+        async fn e2e_allowance(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+            let constructor = Erc20Ref::new(1000, None, None, 0, None, Hash::default());
+            let contract_acc_id = client
+                .instantiate("erc20", &ink_e2e::alice(), constructor, 0, None)
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            let alice_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let bob_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+
+            let opt_out_msg = build_message::<Erc20Ref>(contract_acc_id.clone())
+                .call(|erc20| erc20.set_contract_spenders_only(false));
+            client
+                .call(&ink_e2e::alice(), opt_out_msg, 0, None)
+                .await
+                .expect("set_contract_spenders_only failed");
+
+            let approve_msg = build_message::<Erc20Ref>(contract_acc_id.clone())
+                .call(|erc20| erc20.approve(bob_acc.clone(), 300));
+            client
+                .call(&ink_e2e::alice(), approve_msg, 0, None)
+                .await
+                .expect("approve failed");
+
+            let allowance_msg = build_message::<Erc20Ref>(contract_acc_id.clone())
+                .call(|erc20| erc20.allowance(alice_acc.clone(), bob_acc.clone()));
+            let allowance = client
+                .call_dry_run(&ink_e2e::alice(), &allowance_msg, 0, None)
+                .await;
+            assert_eq!(allowance.return_value(), 300);
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn e2e_burn(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+            let constructor = Erc20Ref::new(1000, None, None, 0, None, Hash::default());
+            let contract_acc_id = client
+                .instantiate("erc20", &ink_e2e::alice(), constructor, 0, None)
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            let alice_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let bob_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+
+            let burn_msg =
+                build_message::<Erc20Ref>(contract_acc_id.clone()).call(|erc20| erc20.burn(100));
+            client
+                .call(&ink_e2e::alice(), burn_msg, 0, None)
+                .await
+                .expect("burn failed");
+
+            let opt_out_msg = build_message::<Erc20Ref>(contract_acc_id.clone())
+                .call(|erc20| erc20.set_contract_spenders_only(false));
+            client
+                .call(&ink_e2e::alice(), opt_out_msg, 0, None)
+                .await
+                .expect("set_contract_spenders_only failed");
+
+            let approve_msg = build_message::<Erc20Ref>(contract_acc_id.clone())
+                .call(|erc20| erc20.approve(bob_acc.clone(), 50));
+            client
+                .call(&ink_e2e::alice(), approve_msg, 0, None)
+                .await
+                .expect("approve failed");
+            let burn_from_msg = build_message::<Erc20Ref>(contract_acc_id.clone())
+                .call(|erc20| erc20.burn_from(alice_acc.clone(), 50));
+            client
+                .call(&ink_e2e::bob(), burn_from_msg, 0, None)
+                .await
+                .expect("burn_from failed");
+
+            let total_supply_msg = build_message::<Erc20Ref>(contract_acc_id.clone())
+                .call(|erc20| erc20.total_supply());
+            let total_supply = client
+                .call_dry_run(&ink_e2e::alice(), &total_supply_msg, 0, None)
+                .await;
+            assert_eq!(total_supply.return_value(), 850);
+
+            let balance_of_msg = build_message::<Erc20Ref>(contract_acc_id.clone())
+                .call(|erc20| erc20.balance_of(alice_acc.clone()));
+            let balance_of_alice = client
+                .call_dry_run(&ink_e2e::alice(), &balance_of_msg, 0, None)
+                .await;
+            assert_eq!(balance_of_alice.return_value(), 850);
+
+            Ok(())
+        }
+
+        #[ink_e2e::test(additional_contracts = "mock_receiver/Cargo.toml")]
+        async fn e2e_transfer_and_call(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+            use mock_receiver::mock_receiver::MockReceiverRef;
+
+            let constructor = Erc20Ref::new(1000, None, None, 0, None, Hash::default());
+            let contract_acc_id = client
+                .instantiate("erc20", &ink_e2e::alice(), constructor, 0, None)
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            let accepting = client
+                .instantiate(
+                    "mock_receiver",
+                    &ink_e2e::alice(),
+                    MockReceiverRef::new(true),
+                    0,
+                    None,
+                )
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            let rejecting = client
+                .instantiate(
+                    "mock_receiver",
+                    &ink_e2e::alice(),
+                    MockReceiverRef::new(false),
+                    0,
+                    None,
+                )
+                .await
+                .expect("instantiate failed")
+                .account_id;
+
+            let transfer_msg = build_message::<Erc20Ref>(contract_acc_id.clone())
+                .call(|erc20| erc20.transfer_and_call(accepting.clone(), 100, Vec::new()));
+            client
+                .call(&ink_e2e::alice(), transfer_msg, 0, None)
+                .await
+                .expect("transfer_and_call failed");
+            let received_msg = build_message::<MockReceiverRef>(accepting.clone())
+                .call(|receiver| receiver.received());
+            let received = client
+                .call_dry_run(&ink_e2e::alice(), &received_msg, 0, None)
+                .await;
+            assert_eq!(received.return_value(), 100);
+
+            let transfer_msg = build_message::<Erc20Ref>(contract_acc_id.clone())
+                .call(|erc20| erc20.transfer_and_call(rejecting.clone(), 100, Vec::new()));
+            let rejected = client
+                .call_dry_run(&ink_e2e::alice(), &transfer_msg, 0, None)
+                .await;
+            assert_eq!(rejected.return_value(), Err(Error::ReceiverRejected));
+
+            let balance_of_msg = build_message::<Erc20Ref>(contract_acc_id.clone())
+                .call(|erc20| erc20.balance_of(rejecting.clone()));
+            let balance_of_rejecting = client
+                .call_dry_run(&ink_e2e::alice(), &balance_of_msg, 0, None)
+                .await;
+            assert_eq!(balance_of_rejecting.return_value(), 0);
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn e2e_transfer_through_client_tx(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
+            use crate::client::tx;
+
+            let constructor = Erc20Ref::new(1000, None, None, 0, None, Hash::default());
+            let contract_acc_id = client
+                .instantiate("erc20", &ink_e2e::alice(), constructor, 0, None)
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            let bob_acc = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+
+            let transfer_msg = build_message::<Erc20Ref>(contract_acc_id.clone())
+                .call(|erc20| erc20.transfer(bob_acc.clone(), 100));
+            let gas_required = client
+                .call_dry_run(&ink_e2e::alice(), &transfer_msg, 0, None)
+                .await
+                .exec_result
+                .gas_required;
+            let limits = tx::Limits {
+                ref_time: gas_required.ref_time(),
+                proof_size: gas_required.proof_size(),
+                storage_deposit: None,
+            };
+            client
+                .runtime_call(
+                    &ink_e2e::alice(),
+                    tx::PALLET,
+                    tx::CALL,
+                    tx::transfer(contract_acc_id.clone(), bob_acc.clone(), 100, limits),
+                )
+                .await
+                .expect("Contracts::call failed");
+
+            let balance_of_msg = build_message::<Erc20Ref>(contract_acc_id.clone())
+                .call(|erc20| erc20.balance_of(bob_acc.clone()));
+            let balance_of_bob = client
+                .call_dry_run(&ink_e2e::alice(), &balance_of_msg, 0, None)
+                .await;
+            assert_eq!(balance_of_bob.return_value(), 100);
+
+            Ok(())
+        }
+    }
+}
+
+/// Off-chain test fixtures shared by the contract's test suites.
+#[cfg(feature = "std")]
+pub mod testing {
+    use crate::erc20::{Approval, CallTrace, Erc20, Transfer};
+    use ink::env::hash::{Blake2x256, CryptoHash, HashOutput};
+    use ink::env::test::{self, EmittedEvent};
+    use ink::env::topics::PrefixedValue;
+    use ink::env::{DefaultEnvironment, Environment};
+    use ink::primitives::{AccountId, Hash};
+
+    type Balance = <DefaultEnvironment as Environment>::Balance;
+
+    /// The contract's event enum, as generated by ink!.
+    pub type Event = <Erc20 as ink::reflect::ContractEventBase>::Type;
+
+    /// Fluent builder that deploys the token in the off-chain environment
+    /// and brings it into a given state through its public messages.
+    ///
+    /// Setup goes through `transfer` and `approve`, so the corresponding
+    /// events are recorded before the test body runs. Owners given an
+    /// allowance opt out of `contract_spenders_only`, as test spenders are
+    /// plain accounts.
+    #[derive(Default)]
+    pub struct Scenario {
+        deployer: Option<AccountId>,
+        contract: Option<AccountId>,
+        balances: Vec<(AccountId, Balance)>,
+        allowances: Vec<(AccountId, AccountId, Balance)>,
+    }
+
+    impl Scenario {
+        pub fn new() -> Self {
+            Default::default()
+        }
+
+        /// Deploys from `deployer` instead of the default `alice`.
+        pub fn deployed_by(mut self, deployer: AccountId) -> Self {
+            self.deployer = Some(deployer);
+            self
+        }
+
+        /// Deploys to `contract`, so the token's own account is one the test
+        /// can name, as vesting, locks and fees need.
+        pub fn deployed_at(mut self, contract: AccountId) -> Self {
+            self.contract = Some(contract);
+            self
+        }
+
+        pub fn with_balance(mut self, account: AccountId, value: Balance) -> Self {
+            self.balances.push((account, value));
+            self
+        }
+
+        pub fn with_allowance(
+            mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+        ) -> Self {
+            self.allowances.push((owner, spender, value));
+            self
+        }
+
+        /// Deploys the token with exactly the requested balances as supply.
+        /// The caller is left set to the deployer.
+        pub fn build(self) -> Erc20 {
+            let deployer = self.deployer.unwrap_or_else(|| accounts().alice);
+            let total_supply = self.balances.iter().map(|(_, value)| value).sum();
+
+            if let Some(contract) = self.contract {
+                test::set_callee::<DefaultEnvironment>(contract);
+            }
+            test::set_caller::<DefaultEnvironment>(deployer);
+            let mut erc20 = Erc20::new(total_supply, None, None, 0, None, Hash::default());
+            for (account, value) in self.balances {
+                if account != deployer {
+                    erc20
+                        .transfer(account, value)
+                        .expect("scenario balance transfer failed");
+                }
+            }
+            for (owner, spender, value) in self.allowances {
+                test::set_caller::<DefaultEnvironment>(owner);
+                erc20.set_contract_spenders_only(false);
+                erc20
+                    .approve(spender, value)
+                    .expect("scenario approval failed");
+            }
+            test::set_caller::<DefaultEnvironment>(deployer);
+
+            erc20
+        }
+    }
+
+    pub fn accounts() -> test::DefaultAccounts<DefaultEnvironment> {
+        test::default_accounts::<DefaultEnvironment>()
+    }
+
+    /// A contract account distinct from every default test account, for
+    /// `Scenario::deployed_at`.
+    pub fn contract_account() -> AccountId {
+        AccountId::from([0xFF; 32])
+    }
+
+    pub fn set_caller(caller: AccountId) {
+        test::set_caller::<DefaultEnvironment>(caller);
+    }
+
+    /// Every event recorded so far by the off-chain environment, except
+    /// `CallTrace`, so event positions do not depend on `telemetry`.
+    pub fn recorded_events() -> Vec<EmittedEvent> {
+        test::recorded_events()
+            .filter(|event| !matches!(decode_event(event), Event::CallTrace(_)))
+            .collect()
+    }
+
+    /// The `CallTrace` events recorded so far, oldest first. Empty unless
+    /// built with `telemetry`.
+    pub fn call_traces() -> Vec<CallTrace> {
+        test::recorded_events()
+            .filter_map(|event| match decode_event(&event) {
+                Event::CallTrace(trace) => Some(trace),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn decode_event(event: &EmittedEvent) -> Event {
+        <Event as scale::Decode>::decode(&mut &event.data[..]).expect("invalid event data")
+    }
+
+    /// The topic ink! records for the `field` topic of `event_name`.
+    pub fn field_topic<T: scale::Encode>(event_name: &str, field: &str, value: &T) -> Vec<u8> {
+        let prefix = format!("Erc20::{event_name}::{field}");
+        let encoded = scale::Encode::encode(&PrefixedValue {
+            prefix: prefix.as_bytes(),
+            value,
+        });
+        encoded_topic(&encoded)
+    }
+
+    /// The leading topic ink! records for every `event_name` event.
+    pub fn signature_topic(event_name: &str) -> Vec<u8> {
+        // The signature is a fixed-size byte array behind an empty prefix,
+        // so it is encoded without a length of its own.
+        let mut encoded = scale::Encode::encode(&b""[..]);
+        encoded.extend_from_slice(format!("Erc20::{event_name}").as_bytes());
+        encoded_topic(&encoded)
+    }
+
+    fn encoded_topic(encoded: &[u8]) -> Vec<u8> {
+        let mut topic = vec![0; 32];
+        if encoded.len() <= topic.len() {
+            topic[..encoded.len()].copy_from_slice(encoded);
+        } else {
+            let mut hash = <Blake2x256 as HashOutput>::Type::default();
+            <Blake2x256 as CryptoHash>::hash(encoded, &mut hash);
+            topic.copy_from_slice(&hash);
+        }
+        topic
+    }
+
+    pub fn assert_transfer_event(
+        event: &EmittedEvent,
+        expected_from: Option<AccountId>,
+        expected_to: Option<AccountId>,
+        expected_value: Balance,
+    ) {
+        match decode_event(event) {
+            Event::Transfer(Transfer { from, to, value }) => {
+                assert_eq!(from, expected_from, "unexpected Transfer.from");
+                assert_eq!(to, expected_to, "unexpected Transfer.to");
+                assert_eq!(value, expected_value, "unexpected Transfer.value");
+            }
+            _ => panic!("expected a Transfer event"),
+        }
+        assert_eq!(
+            event.topics,
+            vec![
+                signature_topic("Transfer"),
+                field_topic("Transfer", "from", &expected_from),
+                field_topic("Transfer", "to", &expected_to),
+            ],
+            "unexpected Transfer topics"
+        );
+    }
+
+    pub fn assert_approval_event(
+        event: &EmittedEvent,
+        expected_from: AccountId,
+        expected_to: AccountId,
+        expected_value: Balance,
+    ) {
+        match decode_event(event) {
+            Event::Approval(Approval { from, to, value }) => {
+                assert_eq!(from, expected_from, "unexpected Approval.from");
+                assert_eq!(to, expected_to, "unexpected Approval.to");
+                assert_eq!(value, expected_value, "unexpected Approval.value");
+            }
+            _ => panic!("expected an Approval event"),
+        }
+        assert_eq!(
+            event.topics,
+            vec![
+                signature_topic("Approval"),
+                field_topic("Approval", "from", &expected_from),
+                field_topic("Approval", "to", &expected_to),
+            ],
+            "unexpected Approval topics"
+        );
+    }
+}
+
+/// Typed access to a deployed token for off-chain Rust services.
+///
+/// Calls are built with the `Erc20Ref` call builder that ink! generates from
+/// the contract itself, so every message, including the `PSP22` trait ones,
+/// is available with its real argument and return types. Messages taking
+/// `&mut self` are built through `call_mut`, queries through `call`:
+///
+/// ```ignore
+/// let mut token = client::at(address);
+/// let params = token.call_mut().transfer(to, 100).params();
+/// let data = client::call_data(&params);
+/// // Submit `data` to the contract, then decode what the node returned.
+/// let result = client::decode_output(&params, &output)?;
+/// ```
+#[cfg(feature = "std")]
+pub mod client {
+    use crate::erc20::Erc20Ref;
+    use ink::env::call::{Call, CallParams, FromAccountId};
+    use ink::env::DefaultEnvironment;
+    use ink::primitives::{AccountId, MessageResult};
+    use scale::{Decode, Encode};
+
+    pub use ink::codegen::TraitCallBuilder;
+
+    /// Parameters of a call built through `Erc20Ref`, returning `R`.
+    pub type Params<Args, R> = CallParams<DefaultEnvironment, Call<DefaultEnvironment>, Args, R>;
+
+    /// The token deployed at `address`.
+    pub fn at(address: AccountId) -> Erc20Ref {
+        <Erc20Ref as FromAccountId<DefaultEnvironment>>::from_account_id(address)
+    }
+
+    /// Input data for the call: the message selector followed by the
+    /// SCALE-encoded arguments.
+    pub fn call_data<Args: Encode, R>(params: &Params<Args, R>) -> Vec<u8> {
+        params.exec_input().encode()
+    }
+
+    /// Decodes the output of the call, as returned by a dry run or found in
+    /// the contract's execution result. The outer `Err` is an ink! dispatch
+    /// error, such as an unknown selector.
+    pub fn decode_output<Args, R: Decode>(
+        _params: &Params<Args, R>,
+        mut output: &[u8],
+    ) -> Result<MessageResult<R>, scale::Error> {
+        MessageResult::<R>::decode(&mut output)
+    }
+
+    /// `Contracts::call` extrinsics for sending calls to the token with
+    /// `subxt`. Keeper bots and the e2e tests submit them the same way.
+    #[cfg(feature = "subxt")]
+    pub mod tx {
+        use super::{call_data, Params};
+        use ink::codegen::TraitCallBuilder;
+        use ink::env::{DefaultEnvironment, Environment};
+        use ink::primitives::{AccountId, Hash};
+        use scale::Encode;
+        use subxt::dynamic::Value;
+        use subxt::tx::DynamicPayload;
+
+        type Balance = <DefaultEnvironment as Environment>::Balance;
+
+        pub const PALLET: &str = "Contracts";
+        pub const CALL: &str = "call";
+
+        /// Resources the extrinsic may use, usually the ones a dry run of
+        /// the same call reported.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct Limits {
+            pub ref_time: u64,
+            pub proof_size: u64,
+            /// Highest storage deposit to charge, or `None` for no limit.
+            pub storage_deposit: Option<Balance>,
+        }
+
+        /// Arguments of `Contracts::call` sending `params` to its callee,
+        /// in the order the pallet declares them. Pass them to
+        /// `ink_e2e::Client::runtime_call`, or to `payload` for `subxt`.
+        pub fn call_values<Args: Encode, R>(
+            params: &Params<Args, R>,
+            limits: Limits,
+        ) -> Vec<Value> {
+            let storage_deposit = match limits.storage_deposit {
+                Some(limit) => Value::unnamed_variant("Some", [Value::u128(limit)]),
+                None => Value::unnamed_variant("None", []),
+            };
+            vec![
+                Value::unnamed_variant("Id", [Value::from_bytes(params.callee())]),
+                Value::u128(0),
+                Value::named_composite([
+                    ("ref_time", Value::u128(limits.ref_time.into())),
+                    ("proof_size", Value::u128(limits.proof_size.into())),
+                ]),
+                storage_deposit,
+                Value::from_bytes(call_data(params)),
+            ]
+        }
+
+        /// Extrinsic payload carrying `values` from `call_values`.
+        pub fn payload(values: Vec<Value>) -> DynamicPayload {
+            subxt::dynamic::tx(PALLET, CALL, values)
+        }
+
+        pub fn transfer(
+            token: AccountId,
+            to: AccountId,
+            value: Balance,
+            limits: Limits,
+        ) -> Vec<Value> {
+            call_values(
+                &super::at(token).call_mut().transfer(to, value).params(),
+                limits,
+            )
+        }
+
+        pub fn approve(
+            token: AccountId,
+            spender: AccountId,
+            value: Balance,
+            limits: Limits,
+        ) -> Vec<Value> {
+            call_values(
+                &super::at(token).call_mut().approve(spender, value).params(),
+                limits,
+            )
+        }
+
+        /// Claims the signer's airdrop allocation.
+        pub fn claim(
+            token: AccountId,
+            index: u32,
+            amount: Balance,
+            proof: Vec<Hash>,
+            limits: Limits,
+        ) -> Vec<Value> {
+            call_values(
+                &super::at(token)
+                    .call_mut()
+                    .claim(index, amount, proof)
+                    .params(),
+                limits,
+            )
+        }
     }
 }
@@ -0,0 +1,58 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+/// Test double for contracts that receive tokens through `transfer_and_call`.
+#[ink::contract]
+pub mod mock_receiver {
+    use ink::prelude::{string::String, vec::Vec};
+
+    /// Mirror of the token's `PSP22Error`, so the hook's return value decodes
+    /// on the caller's side.
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum PSP22Error {
+        Custom(String),
+        InsufficientBalance,
+        InsufficientAllowance,
+        ZeroRecipientAddress,
+        ZeroSenderAddress,
+        SafeTransferCheckFailed(String),
+    }
+
+    /// Accepts or rejects every incoming transfer, depending on how it was
+    /// deployed, and counts what it accepted.
+    #[ink(storage)]
+    pub struct MockReceiver {
+        accept: bool,
+        received: Balance,
+    }
+
+    impl MockReceiver {
+        #[ink(constructor)]
+        pub fn new(accept: bool) -> Self {
+            Self {
+                accept,
+                received: 0,
+            }
+        }
+
+        #[ink(message, selector = 0x0305EEEC)]
+        pub fn on_received(
+            &mut self,
+            _operator: AccountId,
+            _from: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            if !self.accept {
+                return Err(PSP22Error::Custom(String::from("rejected")));
+            }
+            self.received = self.received.saturating_add(value);
+            Ok(())
+        }
+
+        #[ink(message, selector = 0xFF298215)]
+        pub fn received(&self) -> Balance {
+            self.received
+        }
+    }
+}